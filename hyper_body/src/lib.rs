@@ -84,7 +84,7 @@ impl Body {
     /// [`Stream`]: futures::stream::Stream
     pub fn from_stream<S, D, E>(stream: S) -> Self
     where
-        S: Stream<Item = Result<D, E>> + Sync + Send + 'static,
+        S: Stream<Item = Result<D, E>> + Send + 'static,
         D: Into<Bytes>,
         E: Into<BoxError>,
     {
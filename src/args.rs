@@ -1,8 +1,12 @@
+use crate::cors::CorsConfig;
+use crate::http::Format;
+use crate::server::Protocol;
 use clap::CommandFactory;
 pub use clap::Parser;
 use clap_complete::{Generator, Shell, generate};
 use std::io;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 
 fn default_threads() -> NonZeroUsize {
     std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
@@ -35,11 +39,142 @@ pub struct Cli {
     #[arg(long, env, default_value_t = default_threads(), help = "Number of threads to process requests")]
     pub threads: NonZeroUsize,
 
+    #[arg(
+        long,
+        env,
+        conflicts_with = "http2_only",
+        help = "Only serve HTTP/1.1 connections, without h2c upgrades"
+    )]
+    pub http1_only: bool,
+
+    #[arg(
+        long,
+        env,
+        help = "Only serve HTTP/2 connections (h2c prior knowledge)"
+    )]
+    pub http2_only: bool,
+
+    #[arg(
+        long,
+        env,
+        value_enum,
+        default_value_t = Format::Text,
+        help = "Default representation for echo endpoints"
+    )]
+    pub format: Format,
+
+    #[arg(
+        long,
+        env,
+        requires = "tls_key",
+        help = "PEM encoded certificate chain to serve HTTPS with"
+    )]
+    pub tls_cert: Option<PathBuf>,
+
+    #[arg(
+        long,
+        env,
+        requires = "tls_cert",
+        help = "PEM encoded private key for --tls-cert"
+    )]
+    pub tls_key: Option<PathBuf>,
+
+    #[arg(
+        long,
+        env,
+        conflicts_with = "tls_cert",
+        help = "Serve HTTPS with a CA and certificate generated at startup"
+    )]
+    pub tls_self_signed: bool,
+
+    #[arg(
+        long,
+        env,
+        default_value_t = 3443,
+        help = "Port to listen on for HTTPS"
+    )]
+    pub tls_port: u16,
+
+    #[arg(
+        long,
+        env,
+        help = "Compress responses with the encoding negotiated by Accept-Encoding"
+    )]
+    pub compression: bool,
+
+    #[arg(
+        long,
+        env,
+        value_delimiter = ',',
+        help = "Origins allowed to make CORS requests, `*` for any"
+    )]
+    pub cors_origin: Vec<String>,
+
+    #[arg(
+        long,
+        env,
+        value_delimiter = ',',
+        requires = "cors_origin",
+        help = "Methods allowed in CORS requests, mirrors the preflight by default"
+    )]
+    pub cors_method: Vec<String>,
+
+    #[arg(
+        long,
+        env,
+        value_delimiter = ',',
+        requires = "cors_origin",
+        help = "Headers allowed in CORS requests, mirrors the preflight by default"
+    )]
+    pub cors_header: Vec<String>,
+
+    #[arg(
+        long,
+        env,
+        requires = "cors_origin",
+        help = "Allow credentialed CORS requests"
+    )]
+    pub cors_credentials: bool,
+
+    #[arg(
+        long,
+        env,
+        hide_env_values = true,
+        help = "Secret for HS256 tokens under /jwt, generated when unset"
+    )]
+    pub jwt_secret: Option<String>,
+
+    #[arg(
+        long,
+        env,
+        hide_env_values = true,
+        help = "Secret for signing and encrypting /session cookies, \
+                generated when unset"
+    )]
+    pub session_key: Option<String>,
+
     #[arg(long, action = clap::ArgAction::Help, help = "Print help information")]
     pub help: (),
 }
 
 impl Cli {
+    pub fn protocol(&self) -> Protocol {
+        match (self.http1_only, self.http2_only) {
+            (true, _) => Protocol::Http1,
+            (_, true) => Protocol::Http2,
+            _ => Protocol::Auto,
+        }
+    }
+
+    pub fn cors(&self) -> CorsConfig {
+        CorsConfig {
+            origins: self.cors_origin.clone(),
+            methods: self.cors_method.clone(),
+            headers: self.cors_header.clone(),
+            credentials: self.cors_credentials,
+        }
+    }
+
     pub fn print_completions<G: Generator>(&self, generator: G) {
         let mut cmd = Self::command();
         let bin_name = cmd.get_name().to_string();
@@ -63,5 +198,83 @@ mod test {
         assert_eq!(args.host, "0.0.0.0");
         assert_eq!(args.port, 3000u16);
         assert_eq!(args.threads, default_threads());
+        assert_eq!(args.protocol(), Protocol::Auto);
+        assert_eq!(args.format, Format::Text);
+        assert_eq!(args.tls_port, 3443u16);
+        assert!(args.tls_cert.is_none());
+        assert!(!args.tls_self_signed);
+        assert!(!args.compression);
+        assert!(args.cors_origin.is_empty());
+        assert!(!args.cors_credentials);
+    }
+
+    #[test]
+    fn test_args_format() {
+        let args = Cli::parse_from(vec!["httpbox", "--format", "httpbin-json"]);
+        assert_eq!(args.format, Format::Json);
+    }
+
+    #[test]
+    fn test_args_tls() {
+        let args = Cli::parse_from(vec![
+            "httpbox",
+            "--tls-cert",
+            "cert.pem",
+            "--tls-key",
+            "key.pem",
+        ]);
+        assert_eq!(args.tls_cert, Some(PathBuf::from("cert.pem")));
+        assert_eq!(args.tls_key, Some(PathBuf::from("key.pem")));
+
+        let args = Cli::parse_from(vec!["httpbox", "--tls-self-signed"]);
+        assert!(args.tls_self_signed);
+    }
+
+    #[test]
+    fn test_args_tls_cert_requires_key() {
+        let args = Cli::try_parse_from(vec!["httpbox", "--tls-cert", "a.pem"]);
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn test_args_cors() {
+        let args = Cli::parse_from(vec![
+            "httpbox",
+            "--cors-origin",
+            "http://a.test,http://b.test",
+            "--cors-method",
+            "GET,POST",
+            "--cors-credentials",
+        ]);
+        let cors = args.cors();
+        assert_eq!(cors.origins, vec!["http://a.test", "http://b.test"]);
+        assert_eq!(cors.methods, vec!["GET", "POST"]);
+        assert!(cors.headers.is_empty());
+        assert!(cors.credentials);
+    }
+
+    #[test]
+    fn test_args_cors_requires_origin() {
+        let args = Cli::try_parse_from(vec!["httpbox", "--cors-credentials"]);
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn test_args_protocol() {
+        let args = Cli::parse_from(vec!["httpbox", "--http1-only"]);
+        assert_eq!(args.protocol(), Protocol::Http1);
+
+        let args = Cli::parse_from(vec!["httpbox", "--http2-only"]);
+        assert_eq!(args.protocol(), Protocol::Http2);
+    }
+
+    #[test]
+    fn test_args_protocol_conflict() {
+        let args = Cli::try_parse_from(vec![
+            "httpbox",
+            "--http1-only",
+            "--http2-only",
+        ]);
+        assert!(args.is_err());
     }
 }
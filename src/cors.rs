@@ -0,0 +1,265 @@
+use anyhow::Context as _;
+use futures::future::Either;
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::{Method, Request, Response};
+use std::sync::LazyLock;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tower_http::cors::{self, AllowHeaders, AllowMethods, AllowOrigin};
+use uri_path::{Path, path};
+
+/// Route of the endpoint that sets its own `Access-Control-*` headers,
+/// matched the way the router matches it
+static CORS_ENDPOINT: LazyLock<Path> = LazyLock::new(|| path!("cors"));
+
+const WILDCARD: &str = "*";
+
+#[derive(Debug, Default)]
+pub struct CorsConfig {
+    pub origins: Vec<String>,
+    pub methods: Vec<String>,
+    pub headers: Vec<String>,
+    pub credentials: bool,
+}
+
+impl CorsConfig {
+    /// Build the CORS layer, or `None` when no origins are allowed
+    pub fn layer(&self) -> anyhow::Result<Option<CorsLayer>> {
+        if self.origins.is_empty() {
+            return Ok(None);
+        }
+
+        let layer = cors::CorsLayer::new()
+            .allow_origin(self.allow_origin()?)
+            .allow_methods(self.allow_methods()?)
+            .allow_headers(self.allow_headers()?)
+            .allow_credentials(self.credentials);
+
+        Ok(Some(CorsLayer(layer)))
+    }
+
+    // Credentialed requests can't use a wildcard, so mirror the request
+    // instead
+    fn allow_origin(&self) -> anyhow::Result<AllowOrigin> {
+        if self.origins.iter().any(|origin| origin == WILDCARD) {
+            return Ok(match self.credentials {
+                true => AllowOrigin::mirror_request(),
+                false => AllowOrigin::any(),
+            });
+        }
+
+        let origins = self
+            .origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin)
+                    .with_context(|| format!("invalid CORS origin: {origin}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(AllowOrigin::list(origins))
+    }
+
+    fn allow_methods(&self) -> anyhow::Result<AllowMethods> {
+        if self.methods.is_empty() || self.credentials && self.any_method() {
+            return Ok(AllowMethods::mirror_request());
+        }
+        if self.any_method() {
+            return Ok(AllowMethods::any());
+        }
+
+        let methods = self
+            .methods
+            .iter()
+            .map(|method| {
+                method
+                    .parse::<Method>()
+                    .with_context(|| format!("invalid CORS method: {method}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(AllowMethods::list(methods))
+    }
+
+    fn allow_headers(&self) -> anyhow::Result<AllowHeaders> {
+        if self.headers.is_empty() || self.credentials && self.any_header() {
+            return Ok(AllowHeaders::mirror_request());
+        }
+        if self.any_header() {
+            return Ok(AllowHeaders::any());
+        }
+
+        let headers = self
+            .headers
+            .iter()
+            .map(|header| {
+                header
+                    .parse::<HeaderName>()
+                    .with_context(|| format!("invalid CORS header: {header}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(AllowHeaders::list(headers))
+    }
+
+    fn any_method(&self) -> bool {
+        self.methods.iter().any(|method| method == WILDCARD)
+    }
+
+    fn any_header(&self) -> bool {
+        self.headers.iter().any(|header| header == WILDCARD)
+    }
+}
+
+/// Applies CORS to every route except `/cors`, which answers preflights
+/// itself
+#[derive(Clone, Debug)]
+pub struct CorsLayer(cors::CorsLayer);
+
+impl<S> Layer<S> for CorsLayer {
+    type Service = Cors<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Cors(self.0.layer(inner))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Cors<S>(cors::Cors<S>);
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for Cors<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, cors::ResponseFuture<S::Future>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), S::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        if CORS_ENDPOINT.matches(req.uri().path()).is_some() {
+            Either::Left(self.0.get_mut().call(req))
+        } else {
+            Either::Right(self.0.call(req))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CorsConfig;
+    use hyper::header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_METHODS,
+        ACCESS_CONTROL_ALLOW_ORIGIN, ORIGIN,
+    };
+    use hyper::{Request, Response};
+    use std::convert::Infallible;
+    use tower::{Layer, ServiceExt, service_fn};
+
+    fn config(origins: &[&str]) -> CorsConfig {
+        CorsConfig {
+            origins: origins.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    async fn call(
+        config: CorsConfig,
+        req: Request<String>,
+    ) -> Response<String> {
+        let service = service_fn(|_: Request<String>| async {
+            Ok::<_, Infallible>(Response::new(String::from("inner")))
+        });
+        config
+            .layer()
+            .unwrap()
+            .unwrap()
+            .layer(service)
+            .oneshot(req)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_disabled_without_origins() {
+        assert!(CorsConfig::default().layer().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_method() {
+        let config = CorsConfig {
+            methods: vec![String::from("NOT A METHOD")],
+            ..config(&["*"])
+        };
+        assert!(config.layer().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_allowed_origin() {
+        let req = Request::get("/get")
+            .header(ORIGIN, "http://example.com")
+            .body(String::new())
+            .unwrap();
+        let res = call(config(&["http://example.com"]), req).await;
+
+        assert_eq!(
+            res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preflight() {
+        let req = Request::options("/post")
+            .header(ORIGIN, "http://example.com")
+            .header("access-control-request-method", "POST")
+            .body(String::new())
+            .unwrap();
+        let res = call(config(&["*"]), req).await;
+
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_METHODS], "POST");
+        assert_eq!(res.body(), "");
+    }
+
+    #[tokio::test]
+    async fn test_credentials_mirror_origin() {
+        let req = Request::get("/get")
+            .header(ORIGIN, "http://example.com")
+            .body(String::new())
+            .unwrap();
+        let config = CorsConfig {
+            credentials: true,
+            ..config(&["*"])
+        };
+        let res = call(config, req).await;
+
+        assert_eq!(
+            res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://example.com"
+        );
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    }
+
+    #[tokio::test]
+    async fn test_cors_endpoint_bypassed() {
+        for path in ["/cors", "/cors/", "//cors"] {
+            let req = Request::options(path)
+                .header(ORIGIN, "http://example.com")
+                .header("access-control-request-method", "POST")
+                .body(String::new())
+                .unwrap();
+            let res = call(config(&["*"]), req).await;
+
+            assert!(
+                !res.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN),
+                "{path}"
+            );
+            assert_eq!(res.body(), "inner", "{path}");
+        }
+    }
+}
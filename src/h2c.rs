@@ -0,0 +1,321 @@
+//! Cleartext HTTP/2 reached by upgrading an HTTP/1.1 request (RFC 7540 3.2).
+//!
+//! hyper only serves HTTP/2 from its connection preface, so after the 101 the
+//! client preface is rewritten to carry the `HTTP2-Settings` and to replay
+//! the upgraded request as stream 1.
+
+use base64::Engine;
+use base64::alphabet::URL_SAFE;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use hyper::body::Body;
+use hyper::header::{
+    CONNECTION, HOST, HeaderMap, HeaderName, TE, TRANSFER_ENCODING, UPGRADE,
+};
+use hyper::upgrade::OnUpgrade;
+use hyper::{Request as HTTPRequest, Response as HTTPResponse};
+use hyper::{StatusCode, Version};
+use hyper_util::rt::TokioIo;
+use std::io::{self, Cursor};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const FRAME_HEADER_LEN: usize = 9;
+/// The initial SETTINGS_MAX_FRAME_SIZE, which holds until settings change
+const MAX_FRAME_SIZE: usize = 16_384;
+
+const HEADERS: u8 = 0x1;
+const SETTINGS: u8 = 0x4;
+const CONTINUATION: u8 = 0x9;
+const END_STREAM: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+
+static HTTP2_SETTINGS: HeaderName = HeaderName::from_static("http2-settings");
+
+/// base64url, with or without padding
+const SETTINGS_ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+fn has_token(headers: &HeaderMap, name: &HeaderName, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|t| t.trim().eq_ignore_ascii_case(token))
+}
+
+/// Append a frame to `out`
+fn frame(out: &mut Vec<u8>, kind: u8, flags: u8, stream: u32, payload: &[u8]) {
+    out.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+    out.push(kind);
+    out.push(flags);
+    out.extend_from_slice(&stream.to_be_bytes());
+    out.extend_from_slice(payload);
+}
+
+/// An HPACK string literal without Huffman coding (RFC 7541 5.2)
+fn string(out: &mut Vec<u8>, value: &[u8]) {
+    // The length is an integer with a 7-bit prefix (RFC 7541 5.1)
+    const PREFIX_MAX: usize = 0x7f;
+    let mut len = value.len();
+    if len < PREFIX_MAX {
+        out.push(len as u8);
+    } else {
+        out.push(PREFIX_MAX as u8);
+        len -= PREFIX_MAX;
+        while len >= 0x80 {
+            out.push((len % 0x80) as u8 | 0x80);
+            len /= 0x80;
+        }
+        out.push(len as u8);
+    }
+    out.extend_from_slice(value);
+}
+
+/// A field literal without indexing, with a literal name (RFC 7541 6.2.2)
+fn field(out: &mut Vec<u8>, name: &[u8], value: &[u8]) {
+    out.push(0);
+    string(out, name);
+    string(out, value);
+}
+
+/// The request as an HTTP/2 field block, without the connection-specific
+/// fields HTTP/2 forbids (RFC 9113 8.2.2)
+fn field_block<B>(req: &HTTPRequest<B>) -> Vec<u8> {
+    let headers = req.headers();
+    let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+
+    let mut block = vec![];
+    field(&mut block, b":method", req.method().as_str().as_bytes());
+    field(&mut block, b":scheme", b"http");
+    if let Some(host) = headers.get(HOST) {
+        field(&mut block, b":authority", host.as_bytes());
+    }
+    field(&mut block, b":path", path.as_bytes());
+
+    for (name, value) in headers {
+        let connection_specific =
+            [&CONNECTION, &HOST, &TE, &TRANSFER_ENCODING, &UPGRADE]
+                .contains(&name)
+                || name == HTTP2_SETTINGS
+                || name == "keep-alive"
+                || name == "proxy-connection"
+                || has_token(headers, &CONNECTION, name.as_str());
+        if !connection_specific {
+            field(&mut block, name.as_str().as_bytes(), value.as_bytes());
+        }
+    }
+    block
+}
+
+/// An HTTP/1.1 request asking to continue over HTTP/2, which becomes stream 1
+#[derive(Debug)]
+pub struct Upgrade {
+    /// The SETTINGS payload sent in `HTTP2-Settings`
+    settings: Vec<u8>,
+    /// HEADERS and CONTINUATION frames replaying the request on stream 1
+    request: Vec<u8>,
+}
+
+impl Upgrade {
+    /// Only requests without a body are upgraded, the rest carry on over
+    /// HTTP/1.1 as a server is free to decide
+    pub fn from_request<B: Body>(req: &HTTPRequest<B>) -> Option<Self> {
+        let headers = req.headers();
+        if req.version() != Version::HTTP_11
+            || !has_token(headers, &UPGRADE, "h2c")
+            || !has_token(headers, &CONNECTION, "upgrade")
+            || !has_token(headers, &CONNECTION, HTTP2_SETTINGS.as_str())
+            || !req.body().is_end_stream()
+        {
+            return None;
+        }
+
+        let mut values = headers.get_all(&HTTP2_SETTINGS).iter();
+        let (Some(value), None) = (values.next(), values.next()) else {
+            return None;
+        };
+        let settings = SETTINGS_ENGINE.decode(value.as_bytes()).ok()?;
+        if settings.len() % 6 != 0 || settings.len() > MAX_FRAME_SIZE {
+            return None;
+        }
+
+        let block = field_block(req);
+        let mut request = vec![];
+        let mut chunks = block.chunks(MAX_FRAME_SIZE).peekable();
+        let (mut kind, mut flags) = (HEADERS, END_STREAM);
+        while let Some(chunk) = chunks.next() {
+            if chunks.peek().is_none() {
+                flags |= END_HEADERS;
+            }
+            frame(&mut request, kind, flags, 1, chunk);
+            (kind, flags) = (CONTINUATION, 0);
+        }
+
+        Some(Upgrade { settings, request })
+    }
+
+    /// The 101 to answer the upgrade with
+    pub fn switching_protocols() -> HTTPResponse<()> {
+        let mut res = HTTPResponse::new(());
+        *res.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+        res.headers_mut()
+            .insert(CONNECTION, "Upgrade".parse().unwrap());
+        res.headers_mut().insert(UPGRADE, "h2c".parse().unwrap());
+        res
+    }
+
+    /// Read the client preface, and rewrite it to open with a single
+    /// SETTINGS frame, the client's own values coming last so they win,
+    /// followed by the request on stream 1
+    async fn preface<I: AsyncRead + Unpin>(
+        &self,
+        io: &mut I,
+    ) -> io::Result<Vec<u8>> {
+        let invalid =
+            || io::Error::new(io::ErrorKind::InvalidData, "invalid preface");
+
+        let mut preface = [0; PREFACE.len()];
+        io.read_exact(&mut preface).await?;
+        let mut header = [0; FRAME_HEADER_LEN];
+        io.read_exact(&mut header).await?;
+
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]);
+        let len = len as usize;
+        if preface != PREFACE
+            || header[3] != SETTINGS
+            || header[4] != 0
+            || header[5..] != [0; 4]
+            || !len.is_multiple_of(6)
+            || self.settings.len() + len > MAX_FRAME_SIZE
+        {
+            return Err(invalid());
+        }
+        let mut settings = self.settings.clone();
+        settings.resize(self.settings.len() + len, 0);
+        io.read_exact(&mut settings[self.settings.len()..]).await?;
+
+        let mut rewritten = PREFACE.to_vec();
+        frame(&mut rewritten, SETTINGS, 0, 0, &settings);
+        rewritten.extend_from_slice(&self.request);
+        Ok(rewritten)
+    }
+
+    /// The upgraded connection, reading as if the client had opened it with
+    /// HTTP/2 and sent the request on stream 1
+    pub async fn connect(
+        self,
+        on_upgrade: OnUpgrade,
+    ) -> io::Result<impl AsyncRead + AsyncWrite + Unpin + Send + use<>> {
+        let mut io = TokioIo::new(on_upgrade.await.map_err(io::Error::other)?);
+        let preface = self.preface(&mut io).await?;
+
+        let (read, write) = tokio::io::split(io);
+        Ok(tokio::io::join(Cursor::new(preface).chain(read), write))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http_body_util::Empty;
+    use hyper::body::Bytes;
+
+    fn upgrade_request() -> hyper::http::request::Builder {
+        HTTPRequest::builder()
+            .uri("/get?a=1")
+            .header(HOST, "example.com")
+            .header(CONNECTION, "Upgrade, HTTP2-Settings")
+            .header(UPGRADE, "h2c")
+            .header(&HTTP2_SETTINGS, "AAMAAABkAAQAAP__")
+    }
+
+    #[test]
+    fn test_from_request() {
+        let req = upgrade_request().body(Empty::<Bytes>::new()).unwrap();
+        let upgrade = Upgrade::from_request(&req).unwrap();
+
+        // SETTINGS_MAX_CONCURRENT_STREAMS 100, SETTINGS_INITIAL_WINDOW_SIZE
+        assert_eq!(
+            upgrade.settings,
+            [0, 3, 0, 0, 0, 100, 0, 4, 0, 0, 255, 255]
+        );
+
+        let block = field_block(&req);
+        let mut expected = vec![];
+        frame(&mut expected, HEADERS, END_STREAM | END_HEADERS, 1, &block);
+        assert_eq!(upgrade.request, expected);
+    }
+
+    #[test]
+    fn test_from_request_not_upgraded() {
+        let cases = [
+            (UPGRADE, "websocket"),
+            (CONNECTION, "keep-alive"),
+            (HTTP2_SETTINGS.clone(), "AAMAAAB"),
+        ];
+        for (name, value) in cases {
+            let mut req =
+                upgrade_request().body(Empty::<Bytes>::new()).unwrap();
+            req.headers_mut().insert(&name, value.parse().unwrap());
+            assert!(Upgrade::from_request(&req).is_none(), "{name}: {value}");
+        }
+
+        let req = upgrade_request()
+            .version(Version::HTTP_10)
+            .body(Empty::<Bytes>::new())
+            .unwrap();
+        assert!(Upgrade::from_request(&req).is_none());
+
+        let req = upgrade_request().body(String::from("body")).unwrap();
+        assert!(Upgrade::from_request(&req).is_none());
+    }
+
+    #[test]
+    fn test_field_block() {
+        let req = upgrade_request()
+            .header("keep-alive", "timeout=5")
+            .header("x-long", "a".repeat(200))
+            .body(())
+            .unwrap();
+
+        let mut expected = vec![];
+        field(&mut expected, b":method", b"GET");
+        field(&mut expected, b":scheme", b"http");
+        field(&mut expected, b":authority", b"example.com");
+        field(&mut expected, b":path", b"/get?a=1");
+        field(&mut expected, b"x-long", "a".repeat(200).as_bytes());
+        assert_eq!(field_block(&req), expected);
+
+        // 200 is 127 then 73 as a continuation byte
+        let mut long = vec![];
+        string(&mut long, "a".repeat(200).as_bytes());
+        assert_eq!(long[..2], [0x7f, 73]);
+    }
+
+    #[tokio::test]
+    async fn test_preface() {
+        let req = upgrade_request().body(Empty::<Bytes>::new()).unwrap();
+        let upgrade = Upgrade::from_request(&req).unwrap();
+
+        let mut client = PREFACE.to_vec();
+        frame(&mut client, SETTINGS, 0, 0, &[0, 2, 0, 0, 0, 0]);
+        client.extend_from_slice(b"rest");
+        let mut io = Cursor::new(client);
+
+        let rewritten = upgrade.preface(&mut io).await.unwrap();
+        let mut expected = PREFACE.to_vec();
+        let mut settings = upgrade.settings.clone();
+        settings.extend_from_slice(&[0, 2, 0, 0, 0, 0]);
+        frame(&mut expected, SETTINGS, 0, 0, &settings);
+        expected.extend_from_slice(&upgrade.request);
+        assert_eq!(rewritten, expected);
+        assert_eq!(io.position() as usize, io.get_ref().len() - 4);
+
+        let mut io = Cursor::new(b"GET / HTTP/1.1\r\n\r\n".repeat(2));
+        assert!(upgrade.preface(&mut io).await.is_err());
+    }
+}
@@ -18,3 +18,16 @@ where
         self(req).await
     }
 }
+
+/// A handler passing `state` to `handler` along with each request
+pub fn with_state<S, F, Fut>(
+    state: S,
+    handler: F,
+) -> impl Handler + Sync + use<S, F, Fut>
+where
+    S: Clone + Send + Sync + 'static,
+    F: Fn(S, Request) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result> + Send + 'static,
+{
+    move |req| handler(state.clone(), req)
+}
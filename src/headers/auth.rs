@@ -1,18 +1,246 @@
+use crate::headers::authorization::Credentials;
 use crate::headers::{Error, Header, HeaderName, HeaderValue};
 use hyper::http::header;
 use std::fmt;
-use std::iter;
 use std::str::FromStr;
 
-const BASIC_REALM_PREAMBLE: &str = "Basic realm=";
 static WWW_AUTHENTICATE: &HeaderName = &header::WWW_AUTHENTICATE;
 
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+fn skip_whitespace(s: &str) -> &str {
+    s.trim_start_matches([' ', '\t'])
+}
+
+fn skip_separators(s: &str) -> &str {
+    s.trim_start_matches([' ', '\t', ','])
+}
+
+fn parse_token(s: &str) -> (&str, &str) {
+    let end = s.find(|c| !is_token_char(c)).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Parse a `quoted-string`, returning the unescaped value and the remainder
+fn parse_quoted(s: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &s[i + 2..])),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// Parse the next `name=value` pair, or `None` when `s` doesn't start with one
+fn parse_param(s: &str) -> Option<(AuthParam, &str)> {
+    let (name, rest) = parse_token(s);
+    let rest = skip_whitespace(rest).strip_prefix('=')?;
+    let rest = skip_whitespace(rest);
+    if name.is_empty() {
+        return None;
+    }
+
+    let (value, quoted, rest) = match rest.starts_with('"') {
+        true => {
+            let (value, rest) = parse_quoted(rest)?;
+            (value, true, rest)
+        }
+        false => {
+            let (value, rest) = parse_token(rest);
+            (value.to_owned(), false, rest)
+        }
+    };
+    let param = AuthParam {
+        name: name.to_owned(),
+        value,
+        quoted,
+    };
+    Some((param, rest))
+}
+
+/// Parse comma separated auth-params until something else begins
+fn parse_params(mut s: &str) -> (Vec<AuthParam>, &str) {
+    let mut params = vec![];
+    while let Some((param, rest)) = parse_param(skip_separators(s)) {
+        params.push(param);
+        s = rest;
+    }
+    (params, s)
+}
+
+fn parse_challenges(mut s: &str) -> Option<Vec<Challenge>> {
+    let mut challenges = vec![];
+    loop {
+        s = skip_separators(s);
+        if s.is_empty() {
+            break;
+        }
+
+        let (scheme, rest) = parse_token(s);
+        if scheme.is_empty() {
+            return None;
+        }
+        let (params, rest) = parse_params(rest);
+        // Anything left must be the start of another challenge
+        if !rest.is_empty() && !rest.starts_with([' ', '\t', ',']) {
+            return None;
+        }
+        challenges.push(Challenge {
+            scheme: scheme.to_owned(),
+            params,
+        });
+        s = rest;
+    }
+    Some(challenges)
+}
+
+/// An `auth-param`, sent bare when it is a token such as `algorithm`
 #[derive(Clone, Debug, PartialEq)]
-pub struct WWWAuthenticate(BasicRealm);
+pub struct AuthParam {
+    name: String,
+    value: String,
+    quoted: bool,
+}
+
+impl fmt::Display for AuthParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.quoted {
+            return write!(f, "{}={}", self.name, self.value);
+        }
+        write!(f, "{}=\"", self.name)?;
+        for c in self.value.chars() {
+            if c == '"' || c == '\\' {
+                f.write_str("\\")?;
+            }
+            write!(f, "{c}")?;
+        }
+        f.write_str("\"")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Challenge {
+    scheme: String,
+    params: Vec<AuthParam>,
+}
+
+impl Challenge {
+    pub fn new(scheme: &str) -> Self {
+        Challenge {
+            scheme: scheme.to_owned(),
+            params: vec![],
+        }
+    }
+
+    pub fn basic(realm: &str) -> Self {
+        Self::new("Basic").param("realm", realm)
+    }
+
+    /// Add a quoted parameter
+    pub fn param(mut self, name: &str, value: &str) -> Self {
+        self.params.push(AuthParam {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            quoted: true,
+        });
+        self
+    }
+
+    /// Add a parameter whose value is sent as a bare token
+    pub fn token(mut self, name: &str, value: &str) -> Self {
+        self.params.push(AuthParam {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            quoted: false,
+        });
+        self
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|param| param.name.eq_ignore_ascii_case(name))
+            .map(|param| param.value.as_str())
+    }
+
+    /// Whether the challenge can be sent as a header value, which isn't
+    /// the case for schemes or values taken from untrusted input
+    pub fn is_valid(&self) -> bool {
+        let is_token = |s: &str| !s.is_empty() && s.chars().all(is_token_char);
+        is_token(&self.scheme)
+            && self.params.iter().all(|param| {
+                is_token(&param.name)
+                    && match param.quoted {
+                        true => !param.value.chars().any(char::is_control),
+                        false => is_token(&param.value),
+                    }
+            })
+    }
+}
+
+impl fmt::Display for Challenge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.scheme)?;
+        for (i, param) in self.params.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{separator}{param}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Challenge {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Challenge, Error> {
+        match parse_challenges(s).as_deref() {
+            Some([challenge]) => Ok(challenge.clone()),
+            _ => Err(Error::invalid()),
+        }
+    }
+}
+
+impl From<&Challenge> for HeaderValue {
+    fn from(challenge: &Challenge) -> Self {
+        format!("{challenge}").parse().unwrap()
+    }
+}
+
+/// One or more authentication challenges, sent as separate header lines
+#[derive(Clone, Debug, PartialEq)]
+pub struct WWWAuthenticate(Vec<Challenge>);
 
 impl WWWAuthenticate {
+    #[cfg(test)]
     pub fn basic_realm(realm: &str) -> Self {
-        WWWAuthenticate(BasicRealm(realm.to_owned()))
+        WWWAuthenticate(vec![Challenge::basic(realm)])
+    }
+
+    #[cfg(test)]
+    pub fn challenges(&self) -> &[Challenge] {
+        &self.0
+    }
+}
+
+impl From<Challenge> for WWWAuthenticate {
+    fn from(challenge: Challenge) -> Self {
+        WWWAuthenticate(vec![challenge])
+    }
+}
+
+impl From<Vec<Challenge>> for WWWAuthenticate {
+    fn from(challenges: Vec<Challenge>) -> Self {
+        WWWAuthenticate(challenges)
     }
 }
 
@@ -26,62 +254,92 @@ impl Header for WWWAuthenticate {
         Self: Sized,
         I: Iterator<Item = &'i HeaderValue>,
     {
-        values
-            .next()
-            .and_then(|v| v.to_str().ok()?.parse().ok())
-            .map(WWWAuthenticate)
-            .ok_or_else(Error::invalid)
+        let mut challenges = vec![];
+        for value in values {
+            let value = value.to_str().map_err(|_| Error::invalid())?;
+            challenges
+                .extend(parse_challenges(value).ok_or_else(Error::invalid)?);
+        }
+        match challenges.is_empty() {
+            true => Err(Error::invalid()),
+            false => Ok(WWWAuthenticate(challenges)),
+        }
     }
 
     fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
-        values.extend(iter::once((&self.0).into()))
+        values.extend(self.0.iter().map(HeaderValue::from))
     }
 }
 
+/// `Authorization: Digest` credentials (RFC 7616)
 #[derive(Clone, Debug, PartialEq)]
-pub struct BasicRealm(String);
+pub struct Digest(Vec<AuthParam>);
 
-impl From<&BasicRealm> for HeaderValue {
-    fn from(realm: &BasicRealm) -> Self {
-        format!("{realm}").parse().unwrap()
+impl Digest {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|param| param.name.eq_ignore_ascii_case(name))
+            .map(|param| param.value.as_str())
     }
 }
 
-impl fmt::Display for BasicRealm {
+impl FromStr for Digest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Digest, Error> {
+        let (scheme, rest) = parse_token(s);
+        if !scheme.eq_ignore_ascii_case(Self::SCHEME) {
+            return Err(Error::invalid());
+        }
+        match parse_params(rest) {
+            (params, rest) if skip_whitespace(rest).is_empty() => {
+                Ok(Digest(params))
+            }
+            _ => Err(Error::invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Digest {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(BASIC_REALM_PREAMBLE)?;
-        f.write_str("\"")?;
-        f.write_str(&self.0)?;
-        f.write_str("\"")?;
-        Ok(())
+        let challenge = Challenge {
+            scheme: String::from(Self::SCHEME),
+            params: self.0.clone(),
+        };
+        challenge.fmt(f)
     }
 }
 
-impl FromStr for BasicRealm {
-    type Err = Error;
+impl Credentials for Digest {
+    const SCHEME: &'static str = "Digest";
 
-    fn from_str(s: &str) -> Result<BasicRealm, Error> {
-        if s.starts_with(BASIC_REALM_PREAMBLE) {
-            Ok(BasicRealm(
-                s[BASIC_REALM_PREAMBLE.len() + 1..s.len() - 1].to_owned(),
-            ))
-        } else {
-            Err(Error::invalid())
-        }
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        value.to_str().ok()?.parse().ok()
+    }
+
+    fn encode(&self) -> HeaderValue {
+        format!("{self}").parse().unwrap()
+    }
+}
+
+impl From<Challenge> for Digest {
+    fn from(challenge: Challenge) -> Self {
+        Digest(challenge.params)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{BasicRealm, WWWAuthenticate};
-    use crate::headers::{Header, HeaderMapExt};
+    use super::{Challenge, Digest, WWWAuthenticate};
+    use crate::headers::{Authorization, Header, HeaderMapExt};
     use crate::test::headers::encode;
     use hyper::http::HeaderMap;
 
     #[test]
     fn test_encode_basic_realm() {
         assert_eq!(
-            format!("{}", BasicRealm(String::from("Test Realm"))),
+            format!("{}", Challenge::basic("Test Realm")),
             "Basic realm=\"Test Realm\""
         )
     }
@@ -89,18 +347,36 @@ mod test {
     #[test]
     fn test_parse_basic_realm() {
         assert_eq!(
-            "Basic realm=\"Test Realm\"".parse::<BasicRealm>().unwrap(),
-            BasicRealm(String::from("Test Realm")),
+            "Basic realm=\"Test Realm\"".parse::<Challenge>().unwrap(),
+            Challenge::basic("Test Realm"),
         )
     }
 
     #[test]
     fn test_parse_err_basic_realm() {
-        assert!(
-            "Missing realm=\"Test Realm\""
-                .parse::<BasicRealm>()
-                .is_err()
-        )
+        assert!("Basic realm=\"Test Realm".parse::<Challenge>().is_err())
+    }
+
+    #[test]
+    fn test_encode_escaped_param() {
+        assert_eq!(
+            format!("{}", Challenge::basic("a \"quoted\" \\ realm")),
+            "Basic realm=\"a \\\"quoted\\\" \\\\ realm\""
+        );
+        assert_eq!(
+            "Basic realm=\"a \\\"quoted\\\" \\\\ realm\""
+                .parse::<Challenge>()
+                .unwrap(),
+            Challenge::basic("a \"quoted\" \\ realm"),
+        );
+    }
+
+    #[test]
+    fn test_challenge_is_valid() {
+        assert!(Challenge::basic("Test Realm").is_valid());
+        assert!(!Challenge::basic("Test\nRealm").is_valid());
+        assert!(!Challenge::new("Not a scheme").is_valid());
+        assert!(!Challenge::new("Digest").token("stale", "no way").is_valid());
     }
 
     #[test]
@@ -113,6 +389,32 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_encode_www_authenticate_multiple() {
+        let header = WWWAuthenticate::from(vec![
+            Challenge::new("Digest")
+                .param("realm", "Test Realm")
+                .token("algorithm", "MD5"),
+            Challenge::basic("Test Realm"),
+        ]);
+
+        let mut headers = HeaderMap::new();
+        headers.typed_insert(header.clone());
+        let values = headers
+            .get_all(WWWAuthenticate::name())
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                "Digest realm=\"Test Realm\", algorithm=MD5",
+                "Basic realm=\"Test Realm\""
+            ]
+        );
+        assert_eq!(headers.typed_get::<WWWAuthenticate>().unwrap(), header);
+    }
+
     #[test]
     fn test_decode_www_authenticate() {
         let mut headers = HeaderMap::new();
@@ -125,15 +427,53 @@ mod test {
         assert_eq!(header, WWWAuthenticate::basic_realm("Test Realm"))
     }
 
+    #[test]
+    fn test_decode_www_authenticate_list() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            WWWAuthenticate::name(),
+            "Newauth realm=\"apps\", type=1, title=\"Login\", \
+             Basic realm=\"simple\""
+                .parse()
+                .unwrap(),
+        );
+
+        let header = headers.typed_get::<WWWAuthenticate>().unwrap();
+        let challenges = header.challenges();
+        assert_eq!(challenges.len(), 2);
+        assert_eq!(challenges[0].scheme(), "Newauth");
+        assert_eq!(challenges[0].get("type"), Some("1"));
+        assert_eq!(challenges[0].get("title"), Some("Login"));
+        assert_eq!(challenges[1], Challenge::basic("simple"));
+    }
+
     #[test]
     fn test_decode_www_authenticate_invalid() {
         let mut headers = HeaderMap::new();
         headers.insert(
             WWWAuthenticate::name(),
-            "Missing realm=\"Test Realm\"".parse().unwrap(),
+            "Basic realm=\"Test Realm".parse().unwrap(),
         );
 
         let header = headers.typed_try_get::<WWWAuthenticate>();
         assert!(header.is_err())
     }
+
+    #[test]
+    fn test_decode_digest_authorization() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            Authorization::<Digest>::name(),
+            "Digest username=\"Mufasa\", realm=\"test\", nc=00000001, \
+             uri=\"/dir/index.html\""
+                .parse()
+                .unwrap(),
+        );
+
+        let digest = headers.typed_get::<Authorization<Digest>>().unwrap().0;
+        assert_eq!(digest.get("username"), Some("Mufasa"));
+        assert_eq!(digest.get("NC"), Some("00000001"));
+        assert_eq!(digest.get("uri"), Some("/dir/index.html"));
+        assert_eq!(digest.get("cnonce"), None);
+    }
 }
@@ -52,8 +52,9 @@ impl<'a> Deref for Cookie<'a> {
     }
 }
 
+/// One or more cookies, each sent on a `Set-Cookie` line of its own
 #[derive(Clone, Debug, PartialEq)]
-pub struct SetCookie<'a>(pub HTTPCookie<'a>);
+pub struct SetCookie<'a>(pub Vec<HTTPCookie<'a>>);
 
 impl Header for SetCookie<'_> {
     fn name() -> &'static HeaderName {
@@ -65,15 +66,31 @@ impl Header for SetCookie<'_> {
         Self: Sized,
         I: Iterator<Item = &'i HeaderValue>,
     {
-        values
-            .next()
-            .and_then(|v| HTTPCookie::parse(v.to_str().ok()?.to_string()).ok())
-            .map(SetCookie)
-            .ok_or_else(Error::invalid)
+        let cookies = values
+            .map(|v| {
+                v.to_str()
+                    .ok()
+                    .and_then(|s| HTTPCookie::parse(s.to_owned()).ok())
+                    .ok_or_else(Error::invalid)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match cookies.is_empty() {
+            true => Err(Error::invalid()),
+            false => Ok(SetCookie(cookies)),
+        }
     }
 
     fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
-        values.extend(iter::once(self.0.to_string().parse().unwrap()))
+        values.extend(self.iter().map(|c| c.to_string().parse().unwrap()))
+    }
+}
+
+impl<'a> Deref for SetCookie<'a> {
+    type Target = Vec<HTTPCookie<'a>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
     }
 }
 
@@ -152,7 +169,25 @@ mod test {
     #[test]
     fn test_encode_set_cookie() {
         let cookie = HTTPCookie::new("name", "value");
-        assert_eq!(encode(SetCookie(cookie)).to_str().unwrap(), "name=value")
+        assert_eq!(
+            encode(SetCookie(vec![cookie])).to_str().unwrap(),
+            "name=value"
+        )
+    }
+
+    #[test]
+    fn test_encode_multiple_set_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.typed_insert(SetCookie(vec![
+            HTTPCookie::new("first", "value"),
+            HTTPCookie::parse("second=another; Path=/").unwrap(),
+        ]));
+
+        let values = headers.get_all(SetCookie::name()).iter();
+        assert_eq!(
+            values.map(|v| v.to_str().unwrap()).collect::<Vec<_>>(),
+            ["first=value", "second=another; Path=/"]
+        )
     }
 
     #[test]
@@ -161,7 +196,7 @@ mod test {
         headers.insert(SetCookie::name(), "name=value".parse().unwrap());
 
         let header = headers.typed_get::<SetCookie>().unwrap();
-        assert_eq!(header, SetCookie(HTTPCookie::new("name", "value")))
+        assert_eq!(header, SetCookie(vec![HTTPCookie::new("name", "value")]))
     }
 
     #[test]
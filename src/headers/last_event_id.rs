@@ -0,0 +1,60 @@
+use crate::headers::{Error, Header, HeaderName, HeaderValue};
+use std::iter;
+
+static LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LastEventId(pub String);
+
+impl LastEventId {
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Header for LastEventId {
+    fn name() -> &'static HeaderName {
+        &LAST_EVENT_ID
+    }
+
+    fn decode<'i, I>(values: &mut I) -> Result<Self, Error>
+    where
+        Self: Sized,
+        I: Iterator<Item = &'i HeaderValue>,
+    {
+        values
+            .next()
+            .and_then(|v| v.to_str().ok())
+            .map(|v| LastEventId(v.trim().to_owned()))
+            .ok_or_else(Error::invalid)
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        values.extend(iter::once(self.0.parse().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LastEventId;
+    use crate::headers::{Header, HeaderMapExt};
+    use crate::test::headers::encode;
+    use hyper::http::HeaderMap;
+
+    #[test]
+    fn test_encode_last_event_id() {
+        assert_eq!(
+            encode(LastEventId(String::from("42"))).to_str().unwrap(),
+            "42"
+        )
+    }
+
+    #[test]
+    fn test_decode_last_event_id() {
+        let mut headers = HeaderMap::new();
+        headers.insert(LastEventId::name(), " 42 ".parse().unwrap());
+
+        let header = headers.typed_get::<LastEventId>().unwrap();
+        assert_eq!(header.id(), "42")
+    }
+}
@@ -1,6 +1,7 @@
 mod auth;
 mod cookie;
 mod ip;
+mod last_event_id;
 mod location;
 
 pub use self::auth::*;
@@ -8,6 +9,7 @@ pub use self::auth::*;
 pub use self::cookie::*;
 pub use self::cookie::{Cookie, SetCookie}; // Needed to de-conflict glob import from headers;
 pub use self::ip::*;
+pub use self::last_event_id::*;
 pub use self::location::Location; // Needed to de-conflict glob import from headers;
 #[allow(unused_imports)]
 pub use self::location::*;
@@ -0,0 +1,127 @@
+use super::Body;
+use async_compression::tokio::bufread::{
+    BrotliDecoder, BrotliEncoder, GzipDecoder, GzipEncoder, ZlibDecoder,
+    ZlibEncoder, ZstdDecoder, ZstdEncoder,
+};
+use futures::prelude::*;
+use http_body_util::BodyExt;
+use std::io;
+use std::str::FromStr;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::io::{ReaderStream, StreamReader};
+
+async fn read_limited<R: AsyncRead + Unpin>(
+    reader: R,
+    limit: u64,
+) -> io::Result<Vec<u8>> {
+    let mut output = vec![];
+    reader.take(limit + 1).read_to_end(&mut output).await?;
+    if output.len() as u64 > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::FileTooLarge,
+            "decoded body exceeds the size limit",
+        ));
+    }
+    Ok(output)
+}
+
+/// A `Content-Encoding` coding supported for request and response bodies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 4] =
+        [Self::Gzip, Self::Deflate, Self::Brotli, Self::Zstd];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Brotli => "br",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Compress `body` as it streams, flushing whenever the source stalls
+    pub fn encode(self, body: Body) -> Body {
+        let reader = StreamReader::new(
+            body.into_data_stream().map_err(io::Error::other),
+        );
+        match self {
+            Self::Gzip => {
+                Body::from_stream(ReaderStream::new(GzipEncoder::new(reader)))
+            }
+            Self::Deflate => {
+                Body::from_stream(ReaderStream::new(ZlibEncoder::new(reader)))
+            }
+            Self::Brotli => {
+                Body::from_stream(ReaderStream::new(BrotliEncoder::new(reader)))
+            }
+            Self::Zstd => {
+                Body::from_stream(ReaderStream::new(ZstdEncoder::new(reader)))
+            }
+        }
+    }
+
+    /// Decompress `data`, failing once the output grows past `limit` bytes
+    pub async fn decode(self, data: &[u8], limit: u64) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip => read_limited(GzipDecoder::new(data), limit).await,
+            Self::Deflate => read_limited(ZlibDecoder::new(data), limit).await,
+            Self::Brotli => read_limited(BrotliDecoder::new(data), limit).await,
+            Self::Zstd => read_limited(ZstdDecoder::new(data), limit).await,
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Ok(Self::Gzip),
+            "deflate" => Ok(Self::Deflate),
+            "br" => Ok(Self::Brotli),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_encoding() {
+        assert_eq!("gzip".parse(), Ok(Encoding::Gzip));
+        assert_eq!(" X-GZIP".parse(), Ok(Encoding::Gzip));
+        assert_eq!("br".parse(), Ok(Encoding::Brotli));
+        assert_eq!("compress".parse::<Encoding>(), Err(()));
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        for encoding in Encoding::ALL {
+            let body = encoding.encode(Body::from("hello world"));
+            let data = body.collect().await.unwrap().to_bytes();
+
+            let decoded = encoding.decode(&data, 1024).await.unwrap();
+            assert_eq!(decoded, b"hello world", "{encoding:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_limit() {
+        let body = Encoding::Gzip.encode(Body::from(vec![0u8; 100]));
+        let data = body.collect().await.unwrap().to_bytes();
+
+        let err = Encoding::Gzip.decode(&data, 99).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+    }
+}
@@ -0,0 +1,93 @@
+use hyper::header::{ACCEPT, HeaderMap};
+
+/// Representation used by the echo endpoints
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Plain `key = value` text
+    #[default]
+    Text,
+    /// JSON shaped like httpbin's responses
+    #[value(name = "httpbin-json")]
+    Json,
+}
+
+fn quality(params: &str) -> f32 {
+    params
+        .split(';')
+        .filter_map(|param| param.trim().strip_prefix("q="))
+        .find_map(|q| q.trim().parse().ok())
+        .unwrap_or(1.0)
+}
+
+/// Pick a format from the `Accept` header, if it expresses a preference
+pub fn negotiate(headers: &HeaderMap) -> Option<Format> {
+    let mut json = 0.0f32;
+    let mut text = 0.0f32;
+
+    for range in headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+    {
+        let (media_type, params) = range.split_once(';').unwrap_or((range, ""));
+        let q = quality(params);
+        match media_type.trim().to_ascii_lowercase().as_str() {
+            "application/json" => json = json.max(q),
+            "text/plain" => text = text.max(q),
+            _ => {}
+        }
+    }
+
+    if json > text {
+        Some(Format::Json)
+    } else if text > json {
+        Some(Format::Text)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    fn accept(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_negotiate_missing() {
+        assert_eq!(negotiate(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_negotiate_wildcard() {
+        assert_eq!(negotiate(&accept("*/*")), None);
+    }
+
+    #[test]
+    fn test_negotiate_json() {
+        assert_eq!(negotiate(&accept("application/json")), Some(Format::Json));
+    }
+
+    #[test]
+    fn test_negotiate_text() {
+        assert_eq!(negotiate(&accept("text/plain, */*")), Some(Format::Text));
+    }
+
+    #[test]
+    fn test_negotiate_quality() {
+        assert_eq!(
+            negotiate(&accept("application/json;q=0.5, text/plain;q=0.9")),
+            Some(Format::Text)
+        );
+        assert_eq!(
+            negotiate(&accept("text/plain; q=0.1, application/json")),
+            Some(Format::Json)
+        );
+    }
+}
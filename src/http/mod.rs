@@ -3,12 +3,16 @@
 pub use hyper::body::Bytes;
 pub use hyper::http::{StatusCode, Uri};
 
+mod encoding;
 mod error;
+mod format;
 mod request;
 mod response;
 mod stream;
 
+pub use self::encoding::Encoding;
 pub use self::error::Error;
+pub use self::format::Format;
 pub use self::request::*;
 pub use self::response::*;
 pub(crate) use self::stream::*;
@@ -1,6 +1,8 @@
 use super::Body;
+use super::format::{Format, negotiate};
 use crate::headers::{Header, HeaderMapExt};
 use hyper::http::Request as HTTPRequest;
+use hyper::http::uri::Scheme;
 use std::net::SocketAddr;
 use uri_path::PathMatch;
 
@@ -50,6 +52,25 @@ impl Request {
     pub fn client_addr(&self) -> Option<&SocketAddr> {
         self.req.extensions().get::<SocketAddr>()
     }
+
+    /// The echo format preferred by the client, or the server default
+    pub fn format(&self) -> Format {
+        negotiate(self.req.headers()).unwrap_or_else(|| {
+            self.req
+                .extensions()
+                .get::<Format>()
+                .copied()
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn scheme(&self) -> &Scheme {
+        self.req
+            .extensions()
+            .get::<Scheme>()
+            .or_else(|| self.req.uri().scheme())
+            .unwrap_or(&Scheme::HTTP)
+    }
 }
 
 impl core::ops::Deref for Request {
@@ -1,6 +1,7 @@
 use super::{Body, Error, Result, StatusCode, Uri};
 use crate::headers::{ContentType, Header, HeaderMapExt, Location};
 use hyper::http::Response as HTTPResponse;
+use serde_json::Value;
 
 pub type Response = HTTPResponse<Body>;
 
@@ -81,6 +82,21 @@ where
     response().typed_header(ContentType::text()).body(body)
 }
 
+pub fn json<T: serde::Serialize>(value: &T) -> Result {
+    let body = serde_json::to_string_pretty(value)
+        .map_err(|_| internal_server_error())?;
+    response().typed_header(ContentType::json()).body(body)
+}
+
+/// A JSON body describing a failure with `status`
+pub fn json_error(status: StatusCode, value: &Value) -> Error {
+    response()
+        .status(status)
+        .typed_header(ContentType::json())
+        .body(format!("{value:#}"))
+        .map_or_else(|err| err, Error::Failure)
+}
+
 pub fn not_found() -> Error {
     response().status(StatusCode::NOT_FOUND).into()
 }
@@ -94,8 +110,12 @@ pub fn internal_server_error() -> Error {
 }
 
 pub fn redirect_to(uri: Uri) -> Result {
+    redirect_with(StatusCode::FOUND, uri)
+}
+
+pub fn redirect_with(status: StatusCode, uri: Uri) -> Result {
     response()
-        .status(StatusCode::FOUND)
+        .status(status)
         .typed_header(Location::from(uri))
         .into()
 }
@@ -1,15 +1,22 @@
 use crate::args::*;
+use crate::cors::CorsLayer;
+use crate::tls::Tls;
+use futures::prelude::*;
+use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
 use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::{runtime, signal};
 use tower::ServiceBuilder;
+use tower_http::compression::CompressionLayer;
 use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod args;
+mod cors;
+mod h2c;
 mod handler;
 mod headers;
 mod http;
@@ -17,6 +24,7 @@ mod random;
 mod router;
 mod server;
 mod service;
+mod tls;
 
 #[cfg(test)]
 mod test;
@@ -46,21 +54,90 @@ async fn shutdown_signal() {
     }
 }
 
-async fn run_server(addr: SocketAddr) -> std::io::Result<()> {
+async fn run_server(
+    addr: SocketAddr,
+    tls: Option<(SocketAddr, Tls)>,
+    protocol: server::Protocol,
+    format: http::Format,
+    cors: Option<CorsLayer>,
+    compression: bool,
+    config: service::Config,
+) -> std::io::Result<()> {
+    let config = service::Config {
+        ca_certificate: tls
+            .as_ref()
+            .and_then(|(_, tls)| tls.ca_pem())
+            .map(str::to_owned),
+        ..config
+    };
+
+    // Map the compressed body back so the layer can be optional
+    let compression = compression.then(|| {
+        ServiceBuilder::new()
+            .map_response(|res: hyper::Response<_>| res.map(http::Body::new))
+            .layer(CompressionLayer::new())
+    });
+
     let service = ServiceBuilder::new()
         .layer(TraceLayer::new_for_http())
         .layer(TimeoutLayer::with_status_code(
             http::StatusCode::REQUEST_TIMEOUT,
             Duration::from_secs(30),
         ))
-        .service(service::router());
+        .option_layer(compression)
+        .option_layer(cors)
+        .map_request(move |mut req: hyper::Request<_>| {
+            req.extensions_mut().insert(format);
+            req
+        })
+        .service(service::router(config));
+
+    let shutdown = shutdown_signal().boxed().shared();
 
     let listener = TcpListener::bind(addr).await.unwrap();
 
-    let server = server::Server::new(listener, service)
-        .with_graceful_shutdown(shutdown_signal());
+    let server = server::Server::new(listener, service.clone())
+        .with_protocol(protocol)
+        .with_graceful_shutdown(shutdown.clone());
+
+    match tls {
+        Some((tls_addr, tls)) => {
+            let listener = TcpListener::bind(tls_addr).await.unwrap();
+
+            let tls_server = server::Server::new(listener, service)
+                .with_protocol(protocol)
+                .with_tls(tls.acceptor())
+                .with_graceful_shutdown(shutdown);
 
-    server.serve().await
+            future::try_join(server.serve(), tls_server.serve()).await?;
+            Ok(())
+        }
+        None => server.serve().await,
+    }
+}
+
+fn listen_addr(host: &str, port: u16) -> SocketAddr {
+    (host, port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut iter| iter.next_back())
+        .unwrap_or_else(|| panic!("Invalid listening address: {host}:{port}"))
+}
+
+fn self_signed_hosts(host: &str) -> Vec<String> {
+    let mut hosts = vec![
+        String::from("localhost"),
+        String::from("127.0.0.1"),
+        String::from("::1"),
+    ];
+    let unspecified = host
+        .parse::<IpAddr>()
+        .map(|ip| ip.is_unspecified())
+        .unwrap_or(false);
+    if !unspecified && !hosts.iter().any(|h| h == host) {
+        hosts.push(host.to_owned());
+    }
+    hosts
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -80,17 +157,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     let threads = args.threads;
-    let addr = (args.host.clone(), args.port)
-        .to_socket_addrs()
-        .ok()
-        .and_then(|mut iter| iter.next_back())
-        .unwrap_or_else(|| {
-            panic!(
-                "Invalid listening address: {}:{}",
-                args.host.clone(),
-                args.port
-            )
-        });
+    let protocol = args.protocol();
+    let format = args.format;
+    let cors = args.cors().layer()?;
+    let compression = args.compression;
+    let config = service::Config {
+        jwt_secret: args.jwt_secret.clone(),
+        session_key: args.session_key.clone(),
+        ..Default::default()
+    };
+    let addr = listen_addr(&args.host, args.port);
+
+    let tls = if args.tls_self_signed {
+        Some(Tls::self_signed(self_signed_hosts(&args.host), protocol)?)
+    } else if let (Some(cert), Some(key)) = (&args.tls_cert, &args.tls_key) {
+        Some(Tls::from_pem_files(cert, key, protocol)?)
+    } else {
+        None
+    };
+    let tls = tls.map(|tls| (listen_addr(&args.host, args.tls_port), tls));
 
     let runtime = runtime::Builder::new_multi_thread()
         .worker_threads(threads.get())
@@ -99,6 +184,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .build()?;
 
     tracing::info!("Listening on {} with {} threads", addr, threads);
-    let _ = runtime.block_on(run_server(addr));
+    if let Some((tls_addr, _)) = &tls {
+        tracing::info!("Listening for HTTPS on {}", tls_addr);
+    }
+    let _ = runtime.block_on(run_server(
+        addr,
+        tls,
+        protocol,
+        format,
+        cors,
+        compression,
+        config,
+    ));
     Ok(())
 }
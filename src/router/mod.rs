@@ -1,9 +1,13 @@
 use crate::handler::Handler;
+use crate::headers::Allow;
 use crate::http::{
-    Body, Error, Request, Response, internal_server_error, not_found,
+    Body, Error, Request, Response, StatusCode, internal_server_error,
+    not_found, response,
 };
 use futures::prelude::*;
-use hyper::Request as HTTPRequest;
+use hyper::body::Body as _;
+use hyper::header::CONTENT_LENGTH;
+use hyper::{Method, Request as HTTPRequest};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
@@ -12,7 +16,18 @@ use uri_path::PathMatch;
 
 mod routes;
 
-pub use self::routes::{Route, route};
+pub use self::routes::{MethodFilter, Route, route};
+
+/// Methods advertised for routes that accept any method
+const ANY_METHODS: [Method; 7] = [
+    Method::GET,
+    Method::HEAD,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+    Method::OPTIONS,
+];
 
 async fn handle_panics(
     fut: impl Future<Output = crate::http::Result>,
@@ -74,6 +89,61 @@ impl RouterInternal {
             endpoint.route.matches(req).map(|params| (endpoint, params))
         })
     }
+
+    /// Find the GET endpoint that answers a HEAD request
+    fn route_head<B>(
+        &self,
+        req: &HTTPRequest<B>,
+    ) -> Option<(&Endpoint, PathMatch)> {
+        self.endpoints
+            .iter()
+            .filter(|endpoint| endpoint.route.methods().allows(&Method::GET))
+            .find_map(|endpoint| {
+                let params = endpoint.route.path().matches(req.uri().path())?;
+                Some((endpoint, params))
+            })
+    }
+
+    /// Every method accepted by the endpoints sharing this path
+    fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let mut allowed: Vec<Method> = vec![];
+        for endpoint in self.endpoints.iter() {
+            if endpoint.route.path().matches(path).is_none() {
+                continue;
+            }
+            let methods = match endpoint.route.methods() {
+                MethodFilter::Any => return ANY_METHODS.to_vec(),
+                MethodFilter::Only(methods) => methods,
+            };
+            for method in methods {
+                if !allowed.contains(method) {
+                    allowed.push(method.clone());
+                }
+                if method == Method::GET && !allowed.contains(&Method::HEAD) {
+                    allowed.push(Method::HEAD);
+                }
+            }
+        }
+
+        if !allowed.is_empty() && !allowed.contains(&Method::OPTIONS) {
+            allowed.push(Method::OPTIONS);
+        }
+        allowed
+    }
+}
+
+/// Drop the body of a HEAD response while keeping its headers
+fn strip_body(res: Response) -> Response {
+    let (mut parts, body) = res.into_parts();
+    let status = parts.status;
+    let has_length = !(status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED);
+
+    if let (true, Some(len)) = (has_length, body.size_hint().exact()) {
+        parts.headers.entry(CONTENT_LENGTH).or_insert(len.into());
+    }
+    Response::from_parts(parts, Body::empty())
 }
 
 #[derive(Clone)]
@@ -106,15 +176,35 @@ impl<B: Into<Body> + Send + 'static> Service<HTTPRequest<B>> for Router {
 
     fn call(&mut self, req: HTTPRequest<B>) -> Self::Future {
         let router = self.0.clone();
+        let is_head = req.method() == Method::HEAD;
 
         async move {
-            let (endpoint, matched_path) =
-                router.route(&req).ok_or_else(not_found)?;
+            let found = match router.route(&req) {
+                None if is_head => router.route_head(&req),
+                found => found,
+            };
+
+            let Some((endpoint, matched_path)) = found else {
+                let allowed = router.allowed_methods(req.uri().path());
+                if allowed.is_empty() {
+                    return Err(not_found());
+                }
+
+                let status = match req.method() {
+                    &Method::OPTIONS => StatusCode::NO_CONTENT,
+                    _ => StatusCode::METHOD_NOT_ALLOWED,
+                };
+                return Err(response()
+                    .status(status)
+                    .typed_header(allowed.into_iter().collect::<Allow>())
+                    .into());
+            };
 
             let client_req = Request::new(req.map(|b| b.into()), matched_path);
             handle_panics(endpoint.handler.handle(client_req)).await
         }
         .or_else(|e: Error| e.into_result())
+        .map_ok(move |res| if is_head { strip_body(res) } else { res })
         .boxed()
     }
 }
@@ -123,12 +213,94 @@ impl<B: Into<Body> + Send + 'static> Service<HTTPRequest<B>> for Router {
 mod test {
 
     use super::*;
-    use crate::http::Request;
+    use crate::headers::HeaderMapExt;
+    use crate::http::{Request, ok};
+    use crate::test::TestResponseExt;
     use hyper::http::Request as HTTPRequest;
     use hyper::http::StatusCode;
 
     use uri_path::path;
 
+    fn router() -> Router {
+        let handler = |_: Request| async { ok("hello") };
+
+        Router::builder()
+            .install(handler, route(path!("get")))
+            .install(handler, route(path!("post")).method(Method::POST))
+            .install(handler, route(path!("post")).method(Method::PUT))
+            .install(handler, route(path!("any")).any_method())
+            .build()
+    }
+
+    fn req(method: Method, uri: &str) -> HTTPRequest<Body> {
+        HTTPRequest::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn allow(res: &Response) -> Vec<Method> {
+        res.headers().typed_get::<Allow>().unwrap().iter().collect()
+    }
+
+    #[tokio::test]
+    async fn test_not_found() {
+        let res = router().call(req(Method::GET, "/missing")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_method_not_allowed() {
+        let res = router().call(req(Method::GET, "/post")).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(allow(&res), [Method::POST, Method::PUT, Method::OPTIONS]);
+    }
+
+    #[tokio::test]
+    async fn test_method_not_allowed_get_includes_head() {
+        let res = router().call(req(Method::DELETE, "/get")).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(allow(&res), [Method::GET, Method::HEAD, Method::OPTIONS]);
+    }
+
+    #[tokio::test]
+    async fn test_head() {
+        let res = router().call(req(Method::HEAD, "/get")).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_LENGTH], "5");
+        assert_eq!(res.read_body().await.unwrap(), b"");
+    }
+
+    #[tokio::test]
+    async fn test_options() {
+        let res = router().call(req(Method::OPTIONS, "/post")).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(allow(&res), [Method::POST, Method::PUT, Method::OPTIONS]);
+    }
+
+    #[tokio::test]
+    async fn test_options_not_found() {
+        let res = router()
+            .call(req(Method::OPTIONS, "/missing"))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_any_method() {
+        let res = router().call(req(Method::OPTIONS, "/any")).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.read_body().await.unwrap(), b"hello");
+    }
+
     #[tokio::test]
     async fn test_panic() {
         let handler = |_: Request| async {
@@ -2,10 +2,25 @@ use hyper::{Method, Request as HTTPRequest};
 use std::collections::BTreeMap;
 use uri_path::{Path, PathMatch};
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum MethodFilter {
+    Any,
+    Only(Vec<Method>),
+}
+
+impl MethodFilter {
+    pub fn allows(&self, method: &Method) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Only(methods) => methods.contains(method),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RouteBuilder {
     path: Path,
-    method: Method,
+    methods: MethodFilter,
     description: Option<&'static str>,
     example_params: BTreeMap<&'static str, &'static str>,
 }
@@ -14,7 +29,7 @@ impl RouteBuilder {
     pub fn new<P: Into<Path>>(path: P) -> Self {
         RouteBuilder {
             path: path.into(),
-            method: Method::GET,
+            methods: MethodFilter::Only(vec![Method::GET]),
             description: None,
             example_params: BTreeMap::new(),
         }
@@ -26,7 +41,20 @@ impl RouteBuilder {
     }
 
     pub fn method(mut self, method: Method) -> Self {
-        self.method = method;
+        self.methods = MethodFilter::Only(vec![method]);
+        self
+    }
+
+    pub fn methods<I: IntoIterator<Item = Method>>(
+        mut self,
+        methods: I,
+    ) -> Self {
+        self.methods = MethodFilter::Only(methods.into_iter().collect());
+        self
+    }
+
+    pub fn any_method(mut self) -> Self {
+        self.methods = MethodFilter::Any;
         self
     }
 
@@ -40,7 +68,7 @@ impl RouteBuilder {
     }
 
     fn example_path(&self) -> Option<String> {
-        if self.method != Method::GET {
+        if !self.methods.allows(&Method::GET) {
             return None;
         }
 
@@ -51,7 +79,7 @@ impl RouteBuilder {
 #[derive(Debug)]
 pub struct Route {
     path: Path,
-    method: Method,
+    methods: MethodFilter,
     description: Option<&'static str>,
     example_path: Option<String>,
 }
@@ -61,8 +89,8 @@ impl Route {
         &self.path
     }
 
-    pub fn method(&self) -> &Method {
-        &self.method
+    pub fn methods(&self) -> &MethodFilter {
+        &self.methods
     }
 
     pub fn description(&self) -> Option<&'static str> {
@@ -74,7 +102,7 @@ impl Route {
     }
 
     pub fn matches<B>(&self, req: &HTTPRequest<B>) -> Option<PathMatch> {
-        if self.method() != req.method() {
+        if !self.methods().allows(req.method()) {
             return None;
         }
 
@@ -87,7 +115,7 @@ impl From<RouteBuilder> for Route {
         let example_path = route.example_path();
         Route {
             path: route.path,
-            method: route.method,
+            methods: route.methods,
             description: route.description,
             example_path,
         }
@@ -113,4 +141,47 @@ mod test {
 
         assert_eq!(route.example_path(), Some("/foo/bar?id=123&name=baz"));
     }
+
+    #[test]
+    fn test_example_path_non_get() {
+        let route: Route = route(path!("foo")).method(Method::POST).into();
+
+        assert_eq!(route.example_path(), None);
+    }
+
+    #[test]
+    fn test_matches_methods() {
+        let route: Route = route(path!("foo"))
+            .methods([Method::GET, Method::PUT])
+            .into();
+
+        for (method, matches) in [
+            (Method::GET, true),
+            (Method::PUT, true),
+            (Method::POST, false),
+        ] {
+            let req = HTTPRequest::builder()
+                .method(method)
+                .uri("/foo")
+                .body(())
+                .unwrap();
+            assert_eq!(route.matches(&req).is_some(), matches);
+        }
+        assert_eq!(route.example_path(), Some("/foo"));
+    }
+
+    #[test]
+    fn test_matches_any_method() {
+        let route: Route = route(path!("foo" / [*rest])).any_method().into();
+
+        let req = HTTPRequest::builder()
+            .method(Method::PATCH)
+            .uri("/foo/bar/baz")
+            .body(())
+            .unwrap();
+
+        let params = route.matches(&req).unwrap();
+        assert_eq!(params.get("rest").unwrap(), "bar/baz");
+        assert_eq!(route.example_path(), Some("/foo"));
+    }
 }
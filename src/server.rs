@@ -1,17 +1,58 @@
+use crate::h2c;
 use futures::prelude::*;
+use http_body_util::{Either, Empty};
 use hyper::Request as HTTPRequest;
-use hyper::body::{Body, Incoming};
-use hyper::server::conn::http1;
-use hyper_util::rt::TokioIo;
+use hyper::body::{Body, Bytes, Incoming};
+use hyper::http::uri::Scheme;
+use hyper::server::conn::http2;
+use hyper::upgrade::OnUpgrade;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use std::pin::Pin;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpListener;
 use tokio::sync::watch;
+use tokio_rustls::TlsAcceptor;
 use tokio_stream::wrappers::TcpListenerStream;
 use tower::Service;
 use tower::ServiceExt;
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Protocol {
+    /// Detect HTTP/1.1 or HTTP/2 (prior knowledge) per connection, and let
+    /// HTTP/1.1 requests upgrade to h2c
+    #[default]
+    Auto,
+    Http1,
+    Http2,
+}
+
+impl Protocol {
+    /// Whether HTTP/1.1 connections may upgrade to cleartext HTTP/2
+    fn upgrades_h2c(self) -> bool {
+        self == Self::Auto
+    }
+
+    fn builder(self) -> auto::Builder<TokioExecutor> {
+        let builder = auto::Builder::new(TokioExecutor::new());
+        match self {
+            Self::Auto => builder,
+            Self::Http1 => builder.http1_only(),
+            Self::Http2 => builder.http2_only(),
+        }
+    }
+}
+
+/// Either a plain TCP stream or a TLS session wrapping one
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
 pub struct Server<S, F> {
     conn_stream: TcpListenerStream,
     service: S,
+    protocol: Protocol,
+    tls: Option<TlsAcceptor>,
     shutdown_signal: F,
 }
 
@@ -34,10 +75,22 @@ where
         Self {
             conn_stream: TcpListenerStream::new(listener),
             service,
+            protocol: Protocol::default(),
+            tls: None,
             shutdown_signal: future::pending(),
         }
     }
 
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    pub fn with_tls(mut self, acceptor: TlsAcceptor) -> Self {
+        self.tls = Some(acceptor);
+        self
+    }
+
     pub fn with_graceful_shutdown<Fut: Future>(
         self,
         fut: Fut,
@@ -45,6 +98,8 @@ where
         Server {
             conn_stream: self.conn_stream,
             service: self.service,
+            protocol: self.protocol,
+            tls: self.tls,
             shutdown_signal: fut,
         }
     }
@@ -61,65 +116,96 @@ where
         + Send
         + 'static,
     S::Future: Send,
-    RespBody: Body + Send + 'static,
-    RespBody::Data: Send,
-    RespBody::Error: Sync + Send + std::error::Error,
+    RespBody: Body<Data = Bytes> + Send + 'static,
+    RespBody::Error: Sync + Send + std::error::Error + 'static,
     E: Send + Sync + std::error::Error + 'static,
 {
     pub async fn serve(self) -> std::io::Result<()> {
         let (close_tx, close_rx) = watch::channel(());
 
         let service = self.service;
+        let protocol = self.protocol;
+        let tls = self.tls;
+        let scheme = match tls {
+            Some(_) => Scheme::HTTPS,
+            None => Scheme::HTTP,
+        };
+        // h2c is by definition not for TLS, where ALPN picks HTTP/2
+        let upgrades_h2c = protocol.upgrades_h2c() && tls.is_none();
         let conn_stream = self.conn_stream;
 
         let conn_stream = conn_stream
             .take_until(self.shutdown_signal)
             .and_then(|stream| async {
                 let addr = stream.peer_addr()?;
-                let stream = TokioIo::new(stream);
 
-                // Inject the client addr into the request
+                // Inject the client addr and scheme into the request
+                let scheme = scheme.clone();
                 let tower_service = service.clone().map_request(
                     move |mut req: HTTPRequest<_>| {
                         req.extensions_mut().insert(addr);
+                        req.extensions_mut().insert(scheme.clone());
                         req
                     },
                 );
 
                 let mut close_rx = close_rx.clone();
+                let tls = tls.clone();
 
                 tokio::task::spawn(async move {
-                    let hyper_service = hyper::service::service_fn(
-                        move |request: HTTPRequest<_>| {
-                            tower_service.clone().call(request)
+                    let stream: Box<dyn Connection> = match tls {
+                        Some(acceptor) => tokio::select! {
+                            result = acceptor.accept(stream) => match result {
+                                Ok(stream) => Box::new(stream),
+                                Err(err) => {
+                                    tracing::debug!("TLS handshake failed: {err:#}");
+                                    return;
+                                }
+                            },
+                            _ = close_rx.changed() => return,
                         },
-                    );
+                        None => Box::new(stream),
+                    };
+                    let stream = TokioIo::new(stream);
 
-                    let conn = http1::Builder::new()
-                        .serve_connection(stream, hyper_service)
-                        .with_upgrades();
+                    let upgrade_close_rx = close_rx.clone();
+                    let hyper_service = hyper::service::service_fn(
+                        move |mut request: HTTPRequest<_>| {
+                            let service = tower_service.clone();
+                            let upgrade = upgrades_h2c
+                                .then(|| h2c::Upgrade::from_request(&request))
+                                .flatten()
+                                .map(|upgrade| {
+                                    (upgrade, hyper::upgrade::on(&mut request))
+                                });
+                            let close_rx = upgrade_close_rx.clone();
 
-                    let mut conn = std::pin::pin!(conn);
+                            async move {
+                                let Some((upgrade, on_upgrade)) = upgrade else {
+                                    let res = service.oneshot(request).await?;
+                                    return Ok(res.map(Either::Left));
+                                };
 
-                    loop {
-                        tokio::select! {
-                            // Poll the connection. This completes when the client has closed the
-                            // connection, graceful shutdown has completed, or we encounter a TCP error.
-                            result = conn.as_mut() => {
-                                if let Err(err) = result {
-                                    tracing::error!("Error serving connection: {err:#}");
-                                }
-                                break;
-                            }
-                            // Start graceful shutdown when we receive a shutdown signal.
-                            //
-                            // We use a loop to continue polling the connection to allow requests to finish
-                            _ = close_rx.changed() => {
-                                tracing::debug!("signal received, starting graceful shutdown");
-                                conn.as_mut().graceful_shutdown();
+                                tokio::task::spawn(serve_h2c(
+                                    upgrade, on_upgrade, service, close_rx,
+                                ));
+                                let res = h2c::Upgrade::switching_protocols();
+                                let empty = Either::Right(Empty::new());
+                                Ok::<_, E>(res.map(|()| empty))
                             }
-                        }
-                    }
+                        },
+                    );
+
+                    let builder = protocol.builder();
+                    let conn = builder
+                        .serve_connection_with_upgrades(stream, hyper_service);
+
+                    serve_connection(
+                        conn,
+                        auto::UpgradeableConnection::graceful_shutdown,
+                        &mut close_rx,
+                    )
+                    .await;
 
                     // Drop the watch receiver to signal to `main` that this task is done.
                     drop(close_rx);
@@ -143,3 +229,73 @@ where
         Ok(())
     }
 }
+
+/// Serve an upgraded h2c connection until it closes or the server shuts down
+async fn serve_h2c<S, RespBody, E>(
+    upgrade: h2c::Upgrade,
+    on_upgrade: OnUpgrade,
+    service: S,
+    mut close_rx: watch::Receiver<()>,
+) where
+    S: Service<
+            HTTPRequest<Incoming>,
+            Response = hyper::Response<RespBody>,
+            Error = E,
+        > + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+    RespBody: Body<Data = Bytes> + Send + 'static,
+    RespBody::Error: Sync + Send + std::error::Error + 'static,
+    E: Send + Sync + std::error::Error + 'static,
+{
+    let io = match upgrade.connect(on_upgrade).await {
+        Ok(io) => TokioIo::new(io),
+        Err(err) => {
+            tracing::debug!("h2c upgrade failed: {err:#}");
+            return;
+        }
+    };
+
+    let hyper_service =
+        hyper::service::service_fn(move |request: HTTPRequest<_>| {
+            service.clone().oneshot(request)
+        });
+    let conn = http2::Builder::new(TokioExecutor::new())
+        .serve_connection(io, hyper_service);
+    serve_connection(conn, http2::Connection::graceful_shutdown, &mut close_rx)
+        .await;
+}
+
+/// Drive a connection until the client closes it, starting a graceful
+/// shutdown once the server is closing
+async fn serve_connection<C, E>(
+    conn: C,
+    graceful_shutdown: fn(Pin<&mut C>),
+    close_rx: &mut watch::Receiver<()>,
+) where
+    C: Future<Output = Result<(), E>>,
+    E: std::fmt::Display,
+{
+    let mut conn = std::pin::pin!(conn);
+
+    loop {
+        tokio::select! {
+            // Poll the connection. This completes when the client has closed the
+            // connection, graceful shutdown has completed, or we encounter a TCP error.
+            result = conn.as_mut() => {
+                if let Err(err) = result {
+                    tracing::error!("Error serving connection: {err:#}");
+                }
+                break;
+            }
+            // Start graceful shutdown when we receive a shutdown signal.
+            //
+            // We use a loop to continue polling the connection to allow requests to finish
+            _ = close_rx.changed() => {
+                tracing::debug!("signal received, starting graceful shutdown");
+                graceful_shutdown(conn.as_mut());
+            }
+        }
+    }
+}
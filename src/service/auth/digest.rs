@@ -0,0 +1,510 @@
+use super::REALM;
+use crate::handler::Handler;
+use crate::headers::{Authorization, Challenge, Digest, WWWAuthenticate};
+use crate::http::{
+    Error, Request, Result, StatusCode, bad_request, ok, response,
+};
+use async_trait::async_trait;
+use http_body_util::BodyExt;
+use hyper::Method;
+use md5::Md5;
+use serde_derive::Deserialize;
+use sha2::{Digest as _, Sha256};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const NONCE_TTL: Duration = Duration::from_secs(300);
+const MAX_NONCES: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Qop {
+    Auth,
+    AuthInt,
+}
+
+impl Qop {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::AuthInt => "auth-int",
+        }
+    }
+}
+
+impl FromStr for Qop {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "auth" => Ok(Self::Auth),
+            "auth-int" => Ok(Self::AuthInt),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Hash {
+    Md5,
+    Sha256,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Algorithm {
+    hash: Hash,
+    /// `-sess` variants fold the nonces into the user's secret
+    session: bool,
+}
+
+impl Algorithm {
+    fn as_str(self) -> &'static str {
+        match (self.hash, self.session) {
+            (Hash::Md5, false) => "MD5",
+            (Hash::Md5, true) => "MD5-sess",
+            (Hash::Sha256, false) => "SHA-256",
+            (Hash::Sha256, true) => "SHA-256-sess",
+        }
+    }
+
+    fn digest(self, data: &[u8]) -> String {
+        match self.hash {
+            Hash::Md5 => format!("{:x}", Md5::digest(data)),
+            Hash::Sha256 => format!("{:x}", Sha256::digest(data)),
+        }
+    }
+}
+
+impl Default for Algorithm {
+    fn default() -> Self {
+        Self {
+            hash: Hash::Md5,
+            session: false,
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        let (hash, session) = match s.to_ascii_uppercase().as_str() {
+            "MD5" => (Hash::Md5, false),
+            "MD5-SESS" => (Hash::Md5, true),
+            "SHA-256" => (Hash::Sha256, false),
+            "SHA-256-SESS" => (Hash::Sha256, true),
+            _ => return Err(()),
+        };
+        Ok(Self { hash, session })
+    }
+}
+
+/// The `response` a client holding `passwd` should send (RFC 7616 3.4.1)
+fn expected_response(
+    algorithm: Algorithm,
+    credentials: &Digest,
+    passwd: &str,
+    method: &Method,
+    body: &[u8],
+) -> Option<String> {
+    let username = credentials.get("username")?;
+    let realm = credentials.get("realm")?;
+    let uri = credentials.get("uri")?;
+    let nonce = credentials.get("nonce")?;
+    let nc = credentials.get("nc")?;
+    let cnonce = credentials.get("cnonce")?;
+    let qop = credentials.get("qop")?.parse::<Qop>().ok()?;
+
+    let h = |data: String| algorithm.digest(data.as_bytes());
+
+    let mut ha1 = h(format!("{username}:{realm}:{passwd}"));
+    if algorithm.session {
+        ha1 = h(format!("{ha1}:{nonce}:{cnonce}"));
+    }
+    let ha2 = match qop {
+        Qop::Auth => h(format!("{method}:{uri}")),
+        Qop::AuthInt => h(format!("{method}:{uri}:{}", algorithm.digest(body))),
+    };
+    Some(h(format!(
+        "{ha1}:{nonce}:{nc}:{cnonce}:{}:{ha2}",
+        qop.as_str()
+    )))
+}
+
+struct Nonce {
+    issued: Instant,
+    nc: u32,
+}
+
+enum NonceCheck {
+    Fresh,
+    Stale,
+    Replayed,
+}
+
+struct DigestState {
+    opaque: String,
+    ttl: Duration,
+    nonces: Mutex<HashMap<String, Nonce>>,
+}
+
+/// Digest authentication, tracking the nonces it has issued
+#[derive(Clone)]
+pub struct DigestAuth(Arc<DigestState>);
+
+impl Default for DigestAuth {
+    fn default() -> Self {
+        Self::with_ttl(NONCE_TTL)
+    }
+}
+
+impl DigestAuth {
+    fn with_ttl(ttl: Duration) -> Self {
+        DigestAuth(Arc::new(DigestState {
+            opaque: format!("{:032x}", rand::random::<u128>()),
+            ttl,
+            nonces: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    fn issue_nonce(&self) -> String {
+        let nonce = format!("{:032x}", rand::random::<u128>());
+        let mut nonces = self.0.nonces.lock().unwrap();
+
+        nonces.retain(|_, nonce| nonce.issued.elapsed() < self.0.ttl);
+        if nonces.len() >= MAX_NONCES {
+            let oldest = nonces
+                .iter()
+                .min_by_key(|(_, nonce)| nonce.issued)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                nonces.remove(&oldest);
+            }
+        }
+
+        nonces.insert(
+            nonce.clone(),
+            Nonce {
+                issued: Instant::now(),
+                nc: 0,
+            },
+        );
+        nonce
+    }
+
+    /// Record a use of `nonce`, rejecting expired nonces and replayed counts
+    fn check_nonce(&self, nonce: &str, nc: u32) -> NonceCheck {
+        let mut nonces = self.0.nonces.lock().unwrap();
+        match nonces.get_mut(nonce) {
+            Some(state) if state.issued.elapsed() < self.0.ttl => {
+                if nc <= state.nc {
+                    return NonceCheck::Replayed;
+                }
+                state.nc = nc;
+                NonceCheck::Fresh
+            }
+            Some(_) => {
+                nonces.remove(nonce);
+                NonceCheck::Stale
+            }
+            None => NonceCheck::Stale,
+        }
+    }
+
+    fn challenge(&self, qop: Qop, algorithm: Algorithm, stale: bool) -> Error {
+        let mut challenge = Challenge::new("Digest")
+            .param("realm", REALM)
+            .param("qop", qop.as_str())
+            .token("algorithm", algorithm.as_str())
+            .param("nonce", &self.issue_nonce())
+            .param("opaque", &self.0.opaque);
+        if stale {
+            challenge = challenge.token("stale", "true");
+        }
+
+        response()
+            .status(StatusCode::UNAUTHORIZED)
+            .typed_header(WWWAuthenticate::from(challenge))
+            .into()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DigestAuthParams {
+    qop: String,
+    user: String,
+    passwd: String,
+}
+
+#[async_trait]
+impl Handler for DigestAuth {
+    async fn handle(&self, mut req: Request) -> Result {
+        let params =
+            req.params::<DigestAuthParams>().ok_or_else(bad_request)?;
+        let qop = params.qop.parse::<Qop>().map_err(|_| bad_request())?;
+        let algorithm = match req.param::<String>("algorithm") {
+            Some(algorithm) => algorithm.parse::<Algorithm>(),
+            None => Ok(Algorithm::default()),
+        }
+        .map_err(|_| bad_request())?;
+
+        let unauthorized = || self.challenge(qop, algorithm, false);
+
+        let credentials = req
+            .typed_header::<Authorization<Digest>>()
+            .map(|header| header.0)
+            .ok_or_else(unauthorized)?;
+
+        let uri = req.uri().path_and_query().map(|uri| uri.as_str());
+        let matches = |name: &str, expected: Option<&str>| {
+            credentials.get(name).is_some() && credentials.get(name) == expected
+        };
+        let sent_algorithm = credentials.get("algorithm").unwrap_or("MD5");
+        if !matches("username", Some(&params.user))
+            || !matches("realm", Some(REALM))
+            || !matches("opaque", Some(&self.0.opaque))
+            || !matches("qop", Some(qop.as_str()))
+            || !matches("uri", uri)
+            || sent_algorithm.parse::<Algorithm>() != Ok(algorithm)
+        {
+            return Err(unauthorized());
+        }
+
+        let body = match qop {
+            Qop::Auth => Default::default(),
+            Qop::AuthInt => req
+                .body_mut()
+                .collect()
+                .await
+                .map_err(|_| bad_request())?
+                .to_bytes(),
+        };
+
+        let expected = expected_response(
+            algorithm,
+            &credentials,
+            &params.passwd,
+            req.method(),
+            &body,
+        );
+        if expected.is_none()
+            || credentials.get("response") != expected.as_deref()
+        {
+            return Err(unauthorized());
+        }
+
+        let nonce = credentials.get("nonce").unwrap_or_default();
+        let nc = credentials
+            .get("nc")
+            .and_then(|nc| u32::from_str_radix(nc, 16).ok())
+            .ok_or_else(unauthorized)?;
+        match self.check_nonce(nonce, nc) {
+            NonceCheck::Fresh => ok("Authenticated"),
+            NonceCheck::Stale => Err(self.challenge(qop, algorithm, true)),
+            NonceCheck::Replayed => Err(unauthorized()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::headers::HeaderMapExt;
+    use crate::test::*;
+
+    // RFC 7616 section 3.9.1
+    fn rfc_credentials() -> Digest {
+        Challenge::new("Digest")
+            .param("username", "Mufasa")
+            .param("realm", "http-auth@example.org")
+            .param("uri", "/dir/index.html")
+            .token("qop", "auth")
+            .token("nc", "00000001")
+            .param("cnonce", "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ")
+            .param("nonce", "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v")
+            .into()
+    }
+
+    #[test]
+    fn test_expected_response_md5() {
+        let algorithm = "MD5".parse().unwrap();
+        assert_eq!(
+            expected_response(
+                algorithm,
+                &rfc_credentials(),
+                "Circle of Life",
+                &Method::GET,
+                b"",
+            )
+            .unwrap(),
+            "8ca523f5e9506fed4657c9700eebdbec"
+        );
+    }
+
+    #[test]
+    fn test_expected_response_sha256() {
+        let algorithm = "SHA-256".parse().unwrap();
+        assert_eq!(
+            expected_response(
+                algorithm,
+                &rfc_credentials(),
+                "Circle of Life",
+                &Method::GET,
+                b"",
+            )
+            .unwrap(),
+            "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1"
+        );
+    }
+
+    fn digest_request(
+        challenge: &Challenge,
+        algorithm: &str,
+        qop: &str,
+        nc: &str,
+        body: &'static str,
+    ) -> RequestBuilder {
+        let uri = format!("/digest-auth/{qop}/user/passwd/{algorithm}");
+        let mut credentials = Challenge::new("Digest")
+            .param("username", "user")
+            .param("realm", challenge.get("realm").unwrap())
+            .param("uri", &uri)
+            .token("algorithm", algorithm)
+            .token("qop", qop)
+            .token("nc", nc)
+            .param("cnonce", "0a4f113b")
+            .param("nonce", challenge.get("nonce").unwrap())
+            .param("opaque", challenge.get("opaque").unwrap());
+        let response = expected_response(
+            algorithm.parse().unwrap(),
+            &credentials.clone().into(),
+            "passwd",
+            &Method::POST,
+            body.as_bytes(),
+        )
+        .unwrap();
+        credentials = credentials.param("response", &response);
+
+        request()
+            .method(Method::POST)
+            .path(&uri)
+            .param("qop", qop)
+            .param("user", "user")
+            .param("passwd", "passwd")
+            .param("algorithm", algorithm)
+            .typed_header(Authorization(Digest::from(credentials)))
+            .body(body)
+    }
+
+    async fn challenge(handler: &DigestAuth, algorithm: &str) -> Challenge {
+        let res = request()
+            .param("qop", "auth")
+            .param("user", "user")
+            .param("passwd", "passwd")
+            .param("algorithm", algorithm)
+            .handle(handler.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let header = res.headers().typed_get::<WWWAuthenticate>().unwrap();
+        header.challenges()[0].clone()
+    }
+
+    #[tokio::test]
+    async fn test_digest_challenge() {
+        let challenge = challenge(&DigestAuth::default(), "SHA-256").await;
+
+        assert_eq!(challenge.scheme(), "Digest");
+        assert_eq!(challenge.get("realm"), Some(REALM));
+        assert_eq!(challenge.get("qop"), Some("auth"));
+        assert_eq!(challenge.get("algorithm"), Some("SHA-256"));
+        assert!(challenge.get("nonce").is_some());
+        assert!(challenge.get("opaque").is_some());
+        assert_eq!(challenge.get("stale"), None);
+    }
+
+    #[tokio::test]
+    async fn test_digest_authorized() {
+        let handler = DigestAuth::default();
+        let challenge = challenge(&handler, "MD5").await;
+
+        let res = digest_request(&challenge, "MD5", "auth", "00000001", "")
+            .handle(handler.clone())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Replaying the same nonce count is rejected
+        let res = digest_request(&challenge, "MD5", "auth", "00000001", "")
+            .handle(handler.clone())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = digest_request(&challenge, "MD5", "auth", "00000002", "")
+            .handle(handler)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_digest_auth_int() {
+        let handler = DigestAuth::default();
+        let challenge = challenge(&handler, "SHA-256-sess").await;
+
+        let res = digest_request(
+            &challenge,
+            "SHA-256-sess",
+            "auth-int",
+            "00000001",
+            "body",
+        )
+        .handle(handler)
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_digest_wrong_password() {
+        let handler = DigestAuth::default();
+        let challenge = challenge(&handler, "MD5").await;
+
+        let res = digest_request(&challenge, "MD5", "auth", "00000001", "")
+            .param("passwd", "other")
+            .handle(handler)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_digest_stale_nonce() {
+        let handler = DigestAuth::with_ttl(Duration::ZERO);
+        let challenge = challenge(&handler, "MD5").await;
+
+        let res = digest_request(&challenge, "MD5", "auth", "00000001", "")
+            .handle(handler)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let header = res.headers().typed_get::<WWWAuthenticate>().unwrap();
+        assert_eq!(header.challenges()[0].get("stale"), Some("true"));
+    }
+
+    #[tokio::test]
+    async fn test_digest_invalid_qop() {
+        let res = request()
+            .param("qop", "auth-conf")
+            .param("user", "user")
+            .param("passwd", "passwd")
+            .handle(DigestAuth::default())
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,19 +1,17 @@
-use crate::headers::Authorization;
-use crate::headers::WWWAuthenticate;
-use crate::headers::authorization::{Basic, Bearer};
-use crate::http::{Error, Request, Result, StatusCode, ok, response};
+mod digest;
+
+pub use self::digest::DigestAuth;
+
+use crate::headers::authorization::{Basic, Bearer, Credentials};
+use crate::headers::{Authorization, Challenge, WWWAuthenticate};
+use crate::http::{
+    Error, Request, Result, StatusCode, bad_request, not_found, ok, response,
+};
 
 use serde_derive::Deserialize;
 
 pub(crate) const REALM: &str = "User Visible Realm";
 
-fn unauthorized_authenticate() -> Error {
-    response()
-        .status(StatusCode::UNAUTHORIZED)
-        .typed_header(WWWAuthenticate::basic_realm(REALM))
-        .into()
-}
-
 fn unauthorized() -> Error {
     response().status(StatusCode::UNAUTHORIZED).into()
 }
@@ -24,17 +22,82 @@ pub struct BasicAuthParams {
     passwd: String,
 }
 
-pub async fn basic(req: Request) -> Result {
-    let BasicAuthParams { user, passwd } = req
-        .params::<BasicAuthParams>()
-        .ok_or_else(unauthorized_authenticate)?;
+/// Query parameters shaping the challenge sent by `/basic-auth`
+#[derive(Deserialize)]
+pub struct BasicAuthQuery {
+    realm: Option<String>,
+    /// Only `UTF-8` is allowed (RFC 7617 2.1)
+    charset: Option<String>,
+    /// Comma separated schemes to challenge with, each as its own header
+    schemes: Option<String>,
+}
 
-    let headers = req
-        .typed_header::<Authorization<Basic>>()
-        .map(|header| header.0)
-        .filter(|basic| basic.username() == user && basic.password() == passwd);
+impl BasicAuthQuery {
+    fn www_authenticate(&self) -> Option<WWWAuthenticate> {
+        let realm = self.realm.as_deref().unwrap_or(REALM);
+        let charset = match self.charset.as_deref() {
+            Some(charset) if charset.eq_ignore_ascii_case("UTF-8") => {
+                Some("UTF-8")
+            }
+            Some(_) => return None,
+            None => None,
+        };
+
+        let challenges = self
+            .schemes
+            .as_deref()
+            .unwrap_or(Basic::SCHEME)
+            .split(',')
+            .map(|scheme| {
+                let scheme = scheme.trim();
+                let challenge = Challenge::new(scheme).param("realm", realm);
+                match charset {
+                    Some(charset)
+                        if scheme.eq_ignore_ascii_case(Basic::SCHEME) =>
+                    {
+                        challenge.param("charset", charset)
+                    }
+                    _ => challenge,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        challenges
+            .iter()
+            .all(Challenge::is_valid)
+            .then(|| challenges.into())
+    }
+}
+
+fn basic_authorized(req: &Request) -> bool {
+    let Some(BasicAuthParams { user, passwd }) = req.params() else {
+        return false;
+    };
 
-    let _ = headers.ok_or_else(unauthorized_authenticate)?;
+    req.typed_header::<Authorization<Basic>>()
+        .is_some_and(|header| {
+            header.username() == user && header.password() == passwd
+        })
+}
+
+pub async fn basic(req: Request) -> Result {
+    let query = req.query::<BasicAuthQuery>().map_err(|_| bad_request())?;
+    let www_authenticate = query.www_authenticate().ok_or_else(bad_request)?;
+
+    if !basic_authorized(&req) {
+        return response()
+            .status(StatusCode::UNAUTHORIZED)
+            .typed_header(www_authenticate)
+            .into();
+    }
+    ok("Authenticated")
+}
+
+/// Like `basic`, but a 404 keeps browsers from prompting for credentials
+pub async fn hidden_basic(req: Request) -> Result {
+    if !basic_authorized(&req) {
+        return Err(not_found());
+    }
     ok("Authenticated")
 }
 
@@ -118,6 +181,95 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_basic_custom_realm_and_charset() {
+        let res = request()
+            .path("/?realm=Staging&charset=utf-8")
+            .param("user", "my-username")
+            .param("passwd", "my-password")
+            .handle(basic)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers()["www-authenticate"],
+            "Basic realm=\"Staging\", charset=\"UTF-8\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_basic_multiple_challenges() {
+        let res = request()
+            .path("/?schemes=Bearer,Basic&charset=UTF-8")
+            .param("user", "my-username")
+            .param("passwd", "my-password")
+            .handle(basic)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let challenges = res
+            .headers()
+            .get_all("www-authenticate")
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            challenges,
+            [
+                format!("Bearer realm=\"{REALM}\""),
+                format!("Basic realm=\"{REALM}\", charset=\"UTF-8\""),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_basic_invalid_challenge_params() {
+        for path in ["/?charset=latin1", "/?schemes=not%20a%20scheme"] {
+            let res = request()
+                .path(path)
+                .param("user", "my-username")
+                .param("passwd", "my-password")
+                .handle(basic)
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hidden_basic_unauthorized() {
+        let auth = Authorization::basic("my-username", "not-my-password");
+
+        let res = request()
+            .param("user", "my-username")
+            .param("passwd", "my-password")
+            .typed_header(auth)
+            .handle(hidden_basic)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(!res.headers().contains_key("www-authenticate"));
+    }
+
+    #[tokio::test]
+    async fn test_hidden_basic_authorized() {
+        let auth = Authorization::basic("my-username", "my-password");
+
+        let res = request()
+            .param("user", "my-username")
+            .param("passwd", "my-password")
+            .typed_header(auth)
+            .handle(hidden_basic)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn test_bearer_no_authorization() {
         let res = request()
@@ -1,7 +1,8 @@
 use crate::headers::ContentLength;
-use crate::headers::ContentType;
+use crate::headers::{ContentType, ETag};
 use crate::http::{Request, Result, bad_request, body_from_stream, response};
 use crate::random::rng;
+use crate::service::range::{Representation, ranged};
 use futures::prelude::*;
 use rand::Rng;
 use serde_derive::Deserialize;
@@ -21,15 +22,47 @@ pub fn iter_bytes(
     (0..count).map(move |_| rng.random::<u8>())
 }
 
+/// Random bytes that can be regenerated from their seed
+#[derive(Clone)]
+struct SeededBytes {
+    count: u32,
+    seed: u32,
+}
+
+impl Representation for SeededBytes {
+    fn len(&self) -> u64 {
+        self.count.into()
+    }
+
+    fn slice(
+        self,
+        start: u64,
+        end: u64,
+    ) -> impl Iterator<Item = u8> + Send + Sync + 'static {
+        // Offsets are bounded by `count` so they always fit in a u32
+        iter_bytes(end as u32 + 1, Some(self.seed)).skip(start as usize)
+    }
+}
+
 pub async fn bytes(req: Request) -> Result {
     let n = req.param::<u32>("n").ok_or_else(bad_request)?;
     let query = req.query::<BytesQueryParams>().map_err(|_| bad_request())?;
 
-    let data = iter_bytes(n, query.seed).collect::<Vec<u8>>();
-
-    response()
-        .typed_header(ContentType::octet_stream())
-        .body(data)
+    // Only a client supplied seed identifies the same content across requests
+    let etag = match query.seed {
+        Some(seed) => Some(
+            format!("\"bytes-{n}-{seed}\"")
+                .parse::<ETag>()
+                .map_err(|_| bad_request())?,
+        ),
+        None => None,
+    };
+    let content = SeededBytes {
+        count: n,
+        seed: query.seed.unwrap_or_else(rand::random),
+    };
+
+    ranged(&req, content, ContentType::octet_stream(), etag)
 }
 
 pub async fn stream_bytes(req: Request) -> Result {
@@ -68,6 +101,54 @@ mod test {
         assert_eq!(res.read_body().await.unwrap(), [214, 212, 32, 32])
     }
 
+    #[tokio::test]
+    async fn test_bytes_range() {
+        let res = request()
+            .param("n", "4")
+            .path("/?seed=1234")
+            .header(hyper::header::RANGE, "bytes=1-2")
+            .handle(bytes)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers()[hyper::header::ETAG], "\"bytes-4-1234\"");
+        assert_eq!(res.read_body().await.unwrap(), [212, 32])
+    }
+
+    /// Seeded payloads past the first few bytes stay the same too
+    #[tokio::test]
+    async fn test_bytes_seeded_past_4096() {
+        let res = request()
+            .param("n", "5000")
+            .path("/?seed=1234")
+            .handle(bytes)
+            .await
+            .unwrap();
+        let body = res.read_body().await.unwrap();
+        assert_eq!(body[4096..4100], [114, 247, 106, 76]);
+        assert_eq!(body[4996..], [173, 46, 216, 17]);
+
+        let res = request()
+            .param("n", "5000")
+            .path("/?seed=1234")
+            .header(hyper::header::RANGE, "bytes=4096-4099")
+            .handle(bytes)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.read_body().await.unwrap(), [114, 247, 106, 76]);
+
+        let res = request()
+            .param("n", "5000")
+            .path("/?seed=1234")
+            .handle(stream_bytes)
+            .await
+            .unwrap();
+        let body = res.read_body().await.unwrap();
+        assert_eq!(body[4096..4100], [114, 247, 106, 76]);
+    }
+
     #[tokio::test]
     async fn test_bytes_with_bad_seed() {
         let res = request()
@@ -1,17 +1,214 @@
-use crate::headers::{CacheControl, IfModifiedSince, IfNoneMatch};
-use crate::http::{Request, Result, StatusCode, bad_request, response};
-use std::time::Duration;
+use super::cookies::flag;
+use crate::headers::{
+    Age, CacheControl, Date, ETag, Expires, Header, HeaderMapExt, HeaderValue,
+    IfMatch, IfModifiedSince, IfNoneMatch, IfUnmodifiedSince, LastModified,
+};
+use crate::http::{Request, Result, StatusCode, bad_request, json, response};
+use crate::service::echo;
+use hyper::Method;
+use hyper::header::{CACHE_CONTROL, VARY};
+use serde_derive::Deserialize;
+use serde_json::json;
+use std::iter;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-pub async fn cache(req: Request) -> Result {
-    let mut res = response();
-    if req.typed_header::<IfModifiedSince>().is_some()
-        || req.typed_header::<IfNoneMatch>().is_some()
+/// `Last-Modified` of `/cache` unless given, Sat, 01 Jan 2000 00:00:00 GMT
+const LAST_MODIFIED: Duration = Duration::from_secs(946_684_800);
+
+#[derive(Deserialize)]
+pub struct CacheQuery {
+    /// An HTTP-date
+    last_modified: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ETagQuery {
+    weak: Option<bool>,
+}
+
+/// Evaluate the preconditions in the order of RFC 9110 13.2.2, returning the
+/// status answering a failed one
+fn preconditions(
+    req: &Request,
+    etag: &ETag,
+    last_modified: Option<SystemTime>,
+) -> Option<StatusCode> {
+    if let Some(if_match) = req.typed_header::<IfMatch>() {
+        if !if_match.precondition_passes(etag) {
+            return Some(StatusCode::PRECONDITION_FAILED);
+        }
+    } else if let (Some(since), Some(last_modified)) =
+        (req.typed_header::<IfUnmodifiedSince>(), last_modified)
+        && !since.precondition_passes(last_modified)
     {
-        res = res.status(StatusCode::NOT_MODIFIED);
+        return Some(StatusCode::PRECONDITION_FAILED);
+    }
+
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD);
+    if let Some(if_none_match) = req.typed_header::<IfNoneMatch>() {
+        if !if_none_match.precondition_passes(etag) {
+            return Some(match safe {
+                true => StatusCode::NOT_MODIFIED,
+                false => StatusCode::PRECONDITION_FAILED,
+            });
+        }
+    } else if let (true, Some(since), Some(last_modified)) =
+        (safe, req.typed_header::<IfModifiedSince>(), last_modified)
+        && !since.is_modified(last_modified)
+    {
+        return Some(StatusCode::NOT_MODIFIED);
+    }
+
+    None
+}
+
+/// Answers with 304 or 412 as the conditional headers call for, comparing
+/// them against an ETag derived from a fixed or given Last-Modified
+pub async fn cache(req: Request) -> Result {
+    let query = req.query::<CacheQuery>().map_err(|_| bad_request())?;
+    let last_modified = match query.last_modified {
+        Some(date) => HeaderValue::from_str(&date)
+            .ok()
+            .and_then(|v| LastModified::decode(&mut iter::once(&v)).ok())
+            .map(SystemTime::from)
+            .ok_or_else(bad_request)?,
+        None => UNIX_EPOCH + LAST_MODIFIED,
+    };
+    let seconds = last_modified
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let etag = format!("\"{seconds:x}\"").parse::<ETag>().unwrap();
+
+    let mut res = response()
+        .typed_header(etag.clone())
+        .typed_header(LastModified::from(last_modified));
+    if let Some(status) = preconditions(&req, &etag, Some(last_modified)) {
+        res = res.status(status);
     }
     res.into()
 }
 
+/// Answers as a resource with the given ETag would, weak with `weak=true`
+pub async fn etag(req: Request) -> Result {
+    let query = req.query::<ETagQuery>().map_err(|_| bad_request())?;
+    let tag = req.param::<String>("etag").ok_or_else(bad_request)?;
+    let prefix = if query.weak.unwrap_or(false) {
+        "W/"
+    } else {
+        ""
+    };
+    let etag = format!("{prefix}\"{tag}\"")
+        .parse::<ETag>()
+        .map_err(|_| bad_request())?;
+
+    if let Some(status) = preconditions(&req, &etag, None) {
+        return response().status(status).typed_header(etag).into();
+    }
+
+    let mut res = json(&json!({
+        "args": echo::args(&req).map_err(|_| bad_request())?,
+        "headers": echo::headers(&req).map_err(|_| bad_request())?,
+        "origin": echo::origin(&req),
+        "url": echo::url(&req),
+    }))?;
+    res.headers_mut().typed_insert(etag);
+    Ok(res)
+}
+
+/// Responses generated by `/cache-control`, a cache hit leaves it as is
+static GENERATED: AtomicU64 = AtomicU64::new(0);
+
+/// Last second an HTTP date can represent, 9999-12-31T23:59:59Z
+const MAX_HTTP_DATE: Duration = Duration::from_secs(253_402_300_799);
+
+/// Shift `time` by `seconds`, as long as the result fits an HTTP date
+fn shift(time: SystemTime, seconds: i64) -> Option<SystemTime> {
+    let offset = Duration::from_secs(seconds.unsigned_abs());
+    let time = match seconds < 0 {
+        true => time.checked_sub(offset),
+        false => time.checked_add(offset),
+    }?;
+    let since_epoch = time.duration_since(UNIX_EPOCH).ok()?;
+    (since_epoch <= MAX_HTTP_DATE).then_some(time)
+}
+
+/// Cache-Control directives taken from the query as given, along with Vary,
+/// Expires (seconds after Date), Age and a Date skewed by date_skew seconds
+pub async fn cache_control(req: Request) -> Result {
+    let mut directives = vec![];
+    let mut vary = vec![];
+    let mut expires = None;
+    let mut age = None;
+    let mut date_skew = 0;
+
+    for (key, value) in req
+        .query::<Vec<(String, String)>>()
+        .map_err(|_| bad_request())?
+    {
+        match key.as_str() {
+            "no-store" | "no-cache" | "private" | "public"
+            | "must-revalidate" | "immutable"
+                if flag(&value).ok_or_else(bad_request)? =>
+            {
+                directives.push(key);
+            }
+            "max-age"
+            | "s-maxage"
+            | "stale-while-revalidate"
+            | "stale-if-error" => {
+                let seconds =
+                    value.parse::<u64>().map_err(|_| bad_request())?;
+                directives.push(format!("{key}={seconds}"));
+            }
+            "vary" => vary.push(value),
+            "expires" => {
+                expires = Some(value.parse().map_err(|_| bad_request())?);
+            }
+            "age" => age = Some(value.parse().map_err(|_| bad_request())?),
+            "date_skew" => {
+                date_skew = value.parse().map_err(|_| bad_request())?;
+            }
+            // Leave cache busting parameters alone
+            _ => {}
+        }
+    }
+
+    let header =
+        |values: &[String]| HeaderValue::from_str(&values.join(", ")).ok();
+    let cache_control = header(&directives).ok_or_else(bad_request)?;
+    let vary = header(&vary).ok_or_else(bad_request)?;
+    let now = SystemTime::now();
+    let date = shift(now, date_skew).ok_or_else(bad_request)?;
+    let expires = match expires {
+        Some(seconds) => Some(shift(date, seconds).ok_or_else(bad_request)?),
+        None => None,
+    };
+
+    let generated = GENERATED.fetch_add(1, Ordering::Relaxed) + 1;
+    let timestamp = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut res = json(&json!({
+        "generated": generated,
+        "timestamp": timestamp.as_secs_f64(),
+    }))?;
+
+    let headers = res.headers_mut();
+    headers.typed_insert(Date::from(date));
+    if !cache_control.is_empty() {
+        headers.insert(CACHE_CONTROL, cache_control);
+    }
+    if !vary.is_empty() {
+        headers.insert(VARY, vary);
+    }
+    if let Some(expires) = expires {
+        headers.typed_insert(Expires::from(expires));
+    }
+    if let Some(age) = age {
+        headers.typed_insert(Age::from_secs(age));
+    }
+    Ok(res)
+}
+
 pub async fn set_cache(req: Request) -> Result {
     let n = req.param::<u64>("n").ok_or_else(bad_request)?;
 
@@ -23,10 +220,8 @@ pub async fn set_cache(req: Request) -> Result {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::headers::HeaderMapExt;
     use crate::test::*;
     use hyper::http::StatusCode;
-    use std::time::SystemTime;
 
     #[tokio::test]
     async fn test_cache_no_headers() {
@@ -54,6 +249,165 @@ mod test {
         assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
     }
 
+    #[tokio::test]
+    async fn test_cache_validators() {
+        let res = request()
+            .path("/?last_modified=Wed,%2021%20Oct%202015%2007:28:00%20GMT")
+            .handle(cache)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["etag"], "\"56273e80\"");
+        assert_eq!(
+            res.headers()["last-modified"],
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cache_conditions() {
+        let etag = || "\"386d4380\"".parse::<ETag>().unwrap();
+        let other = || "\"other\"".parse::<ETag>().unwrap();
+        let weak = || "W/\"386d4380\"".parse::<ETag>().unwrap();
+        let before = UNIX_EPOCH + LAST_MODIFIED - Duration::from_secs(60);
+
+        let cases = [
+            (request().typed_header(IfNoneMatch::from(other())), 200),
+            (request().typed_header(IfNoneMatch::from(weak())), 304),
+            (request().typed_header(IfModifiedSince::from(before)), 200),
+            (request().typed_header(IfMatch::from(etag())), 200),
+            (request().typed_header(IfMatch::from(weak())), 412),
+            (request().typed_header(IfMatch::from(other())), 412),
+            (request().typed_header(IfUnmodifiedSince::from(before)), 412),
+            (
+                request()
+                    .typed_header(IfNoneMatch::from(other()))
+                    .typed_header(IfModifiedSince::from(SystemTime::now())),
+                200,
+            ),
+        ];
+        for (i, (req, status)) in cases.into_iter().enumerate() {
+            let res = req.handle(cache).await.unwrap();
+            assert_eq!(res.status().as_u16(), status, "case {i}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_etag() {
+        let res = request().param("etag", "abc").handle(etag).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["etag"], "\"abc\"");
+        let body = res.read_body_json().await.unwrap();
+        assert!(body["headers"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_etag_conditions() {
+        let tag = |s: &str| s.parse::<ETag>().unwrap();
+        let cases = [
+            ("/", Method::GET, IfNoneMatch::from(tag("\"abc\"")), 304),
+            ("/", Method::GET, IfNoneMatch::from(tag("W/\"abc\"")), 304),
+            ("/", Method::GET, IfNoneMatch::from(tag("\"xyz\"")), 200),
+            ("/", Method::PUT, IfNoneMatch::from(tag("\"abc\"")), 412),
+            ("/", Method::PUT, IfNoneMatch::any(), 412),
+            ("/?weak=true", Method::GET, IfNoneMatch::any(), 304),
+        ];
+        for (path, method, header, status) in cases {
+            let res = request()
+                .path(path)
+                .method(method)
+                .param("etag", "abc")
+                .typed_header(header)
+                .handle(etag)
+                .await
+                .unwrap();
+            assert_eq!(res.status().as_u16(), status, "{path}");
+        }
+
+        let cases = [
+            ("/", IfMatch::from(tag("\"abc\"")), 200),
+            ("/", IfMatch::from(tag("\"xyz\"")), 412),
+            ("/", IfMatch::any(), 200),
+            ("/?weak=true", IfMatch::from(tag("W/\"abc\"")), 412),
+        ];
+        for (path, header, status) in cases {
+            let res = request()
+                .path(path)
+                .method(Method::PUT)
+                .param("etag", "abc")
+                .typed_header(header)
+                .handle(etag)
+                .await
+                .unwrap();
+            assert_eq!(res.status().as_u16(), status, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_control() {
+        let res = request()
+            .path(
+                "/?public&max-age=60&s-maxage=120&no-cache=false\
+                 &stale-while-revalidate=30&stale-if-error=300&immutable\
+                 &vary=Accept&vary=Cookie&age=10&expires=60&date_skew=-30\
+                 &_=1",
+            )
+            .handle(cache_control)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let headers = res.headers();
+        assert_eq!(
+            headers[CACHE_CONTROL],
+            "public, max-age=60, s-maxage=120, stale-while-revalidate=30, \
+             stale-if-error=300, immutable"
+        );
+        assert_eq!(headers[VARY], "Accept, Cookie");
+        assert_eq!(headers.typed_get::<Age>().unwrap().as_secs(), 10);
+
+        let date = SystemTime::from(headers.typed_get::<Date>().unwrap());
+        let skew = SystemTime::now().duration_since(date).unwrap();
+        assert!((29..=31).contains(&skew.as_secs()));
+        let expires = SystemTime::from(headers.typed_get::<Expires>().unwrap());
+        assert_eq!(expires.duration_since(date).unwrap().as_secs(), 60);
+    }
+
+    #[tokio::test]
+    async fn test_cache_control_changing_body() {
+        let mut generated = vec![];
+        for _ in 0..2 {
+            let res = request().handle(cache_control).await.unwrap();
+            assert!(!res.headers().contains_key(CACHE_CONTROL));
+            let body = res.read_body_json().await.unwrap();
+            generated.push(body["generated"].as_u64().unwrap());
+        }
+        assert!(generated[1] > generated[0]);
+    }
+
+    #[tokio::test]
+    async fn test_cache_control_invalid() {
+        for query in [
+            "max-age=soon",
+            "no-store=maybe",
+            "date_skew=x",
+            "date_skew=-2000000000",
+            "date_skew=300000000000",
+            "expires=300000000000",
+            "date_skew=-1000000000&expires=-1000000000",
+            "expires=-9223372036854775808",
+        ] {
+            let res = request()
+                .path(&format!("/?{query}"))
+                .handle(cache_control)
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{query}");
+        }
+    }
+
     #[tokio::test]
     async fn test_set_cache() {
         let res = request().param("n", "30").handle(set_cache).await.unwrap();
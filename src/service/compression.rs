@@ -0,0 +1,95 @@
+use crate::http::{Encoding, Request, Result, bad_request, json};
+use crate::service::echo;
+use hyper::header::{CONTENT_ENCODING, HeaderValue};
+use serde_json::json;
+
+async fn compressed(req: Request, encoding: Encoding, key: &str) -> Result {
+    let mut res = json(&json!({
+        key: true,
+        "headers": echo::headers(&req).map_err(|_| bad_request())?,
+        "method": req.method().as_str(),
+        "origin": echo::origin(&req),
+    }))?
+    .map(|body| encoding.encode(body));
+
+    res.headers_mut().insert(
+        CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    Ok(res)
+}
+
+pub async fn gzip(req: Request) -> Result {
+    compressed(req, Encoding::Gzip, "gzipped").await
+}
+
+pub async fn deflate(req: Request) -> Result {
+    compressed(req, Encoding::Deflate, "deflated").await
+}
+
+pub async fn brotli(req: Request) -> Result {
+    compressed(req, Encoding::Brotli, "brotli").await
+}
+
+pub async fn zstd(req: Request) -> Result {
+    compressed(req, Encoding::Zstd, "zstd").await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use async_compression::tokio::bufread::{
+        BrotliDecoder, GzipDecoder, ZlibDecoder, ZstdDecoder,
+    };
+    use hyper::http::StatusCode;
+    use tokio::io::{AsyncRead, AsyncReadExt};
+
+    async fn decode<R: AsyncRead + Unpin>(mut decoder: R) -> serde_json::Value {
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).await.unwrap();
+        serde_json::from_slice(&output).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_gzip() {
+        let res = request().handle(gzip).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+        let body = res.read_body().await.unwrap();
+        let value = decode(GzipDecoder::new(&body[..])).await;
+        assert_eq!(value["gzipped"], true);
+        assert_eq!(value["method"], "GET");
+    }
+
+    #[tokio::test]
+    async fn test_deflate() {
+        let res = request().handle(deflate).await.unwrap();
+
+        assert_eq!(res.headers()[CONTENT_ENCODING], "deflate");
+        let body = res.read_body().await.unwrap();
+        let value = decode(ZlibDecoder::new(&body[..])).await;
+        assert_eq!(value["deflated"], true);
+    }
+
+    #[tokio::test]
+    async fn test_brotli() {
+        let res = request().handle(brotli).await.unwrap();
+
+        assert_eq!(res.headers()[CONTENT_ENCODING], "br");
+        let body = res.read_body().await.unwrap();
+        let value = decode(BrotliDecoder::new(&body[..])).await;
+        assert_eq!(value["brotli"], true);
+    }
+
+    #[tokio::test]
+    async fn test_zstd() {
+        let res = request().handle(zstd).await.unwrap();
+
+        assert_eq!(res.headers()[CONTENT_ENCODING], "zstd");
+        let body = res.read_body().await.unwrap();
+        let value = decode(ZstdDecoder::new(&body[..])).await;
+        assert_eq!(value["zstd"], true);
+    }
+}
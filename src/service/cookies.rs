@@ -1,11 +1,31 @@
-use crate::headers::{Cookie, SetCookie};
-use crate::http::{Request, Result, bad_request, ok, response};
-use cookie::Cookie as HTTPCookie;
+use crate::headers::{
+    Cookie, Expires, Header, HeaderValue, Location, SetCookie,
+};
+use crate::http::{
+    Error, Format, Request, Result, StatusCode, Uri, bad_request, json, ok,
+    response,
+};
+use cookie::time::{Duration, OffsetDateTime};
+use cookie::{Cookie as HTTPCookie, SameSite};
 use itertools::Itertools;
+use serde_json::{Map, Value, json};
+use std::iter;
+use std::time::SystemTime;
 
 pub async fn cookies(req: Request) -> Result {
     let cookies = req.typed_header::<Cookie>();
 
+    if req.format() == Format::Json {
+        let cookies = cookies
+            .iter()
+            .flat_map(|cookie| cookie.iter())
+            .map(|cookie| {
+                (cookie.name().to_owned(), Value::from(cookie.value()))
+            })
+            .collect::<Map<_, _>>();
+        return json(&json!({ "cookies": cookies }));
+    }
+
     let body = cookies
         .iter()
         .flat_map(|cookie| cookie.iter())
@@ -17,16 +37,175 @@ pub async fn cookies(req: Request) -> Result {
     ok(body)
 }
 
-pub async fn set_cookies(req: Request) -> Result {
-    let mut res = response();
-    for (k, v) in req
-        .query::<Vec<(String, String)>>()
-        .map_err(|_| bad_request())?
-    {
-        res = res.typed_header(SetCookie(HTTPCookie::new(k, v)));
+/// Attributes given alongside the cookies, applied to each of them
+#[derive(Default)]
+struct Attributes {
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    expires: Option<OffsetDateTime>,
+    secure: Option<bool>,
+    http_only: Option<bool>,
+    same_site: Option<SameSite>,
+    partitioned: Option<bool>,
+}
+
+impl Attributes {
+    fn apply(&self, cookie: &mut HTTPCookie<'static>) {
+        if let Some(path) = &self.path {
+            cookie.set_path(path.clone());
+        }
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        if let Some(max_age) = self.max_age {
+            cookie.set_max_age(max_age);
+        }
+        if let Some(expires) = self.expires {
+            cookie.set_expires(expires);
+        }
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(self.http_only);
+        cookie.set_same_site(self.same_site);
+        cookie.set_partitioned(self.partitioned);
+    }
+}
+
+/// A flag is set when given without a value, `true` or `false` spelt out
+pub(super) fn flag(value: &str) -> Option<bool> {
+    match value {
+        "" | "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn http_date(value: &str) -> Option<OffsetDateTime> {
+    let value = HeaderValue::from_str(value).ok()?;
+    let expires = Expires::decode(&mut iter::once(&value)).ok()?;
+    Some(SystemTime::from(expires).into())
+}
+
+/// The query of `/cookies/set` and `/cookies/delete`: cookie attributes,
+/// named as in `Set-Cookie` and matched case-insensitively, `redirect`, and
+/// everything else as cookies
+#[derive(Default)]
+struct CookieQuery {
+    cookies: Vec<(String, String)>,
+    attributes: Attributes,
+    redirect: bool,
+}
+
+impl CookieQuery {
+    #[allow(clippy::result_large_err)]
+    fn parse(req: &Request) -> std::result::Result<Self, Error> {
+        let mut query = CookieQuery::default();
+        let attributes = &mut query.attributes;
+
+        for (key, value) in req
+            .query::<Vec<(String, String)>>()
+            .map_err(|_| bad_request())?
+        {
+            match key.to_ascii_lowercase().as_str() {
+                "path" => attributes.path = Some(value),
+                "domain" => attributes.domain = Some(value),
+                "max-age" => {
+                    let seconds = value.parse().map_err(|_| bad_request())?;
+                    attributes.max_age = Some(Duration::seconds(seconds));
+                }
+                "expires" => {
+                    attributes.expires =
+                        Some(http_date(&value).ok_or_else(bad_request)?);
+                }
+                "secure" => {
+                    attributes.secure =
+                        Some(flag(&value).ok_or_else(bad_request)?);
+                }
+                "httponly" => {
+                    attributes.http_only =
+                        Some(flag(&value).ok_or_else(bad_request)?);
+                }
+                "samesite" => {
+                    let same_site = match value.to_ascii_lowercase().as_str() {
+                        "strict" => SameSite::Strict,
+                        "lax" => SameSite::Lax,
+                        "none" => SameSite::None,
+                        _ => return Err(bad_request()),
+                    };
+                    attributes.same_site = Some(same_site);
+                }
+                "partitioned" => {
+                    attributes.partitioned =
+                        Some(flag(&value).ok_or_else(bad_request)?);
+                }
+                "redirect" => {
+                    query.redirect = flag(&value).ok_or_else(bad_request)?;
+                }
+                _ => query.cookies.push((key, value)),
+            }
+        }
+
+        Ok(query)
     }
 
-    res.into()
+    /// Respond with the cookies, following up with a redirect to `/cookies`
+    /// when asked for, like httpbin does
+    #[allow(clippy::result_large_err)]
+    fn respond(self, cookies: Vec<HTTPCookie<'static>>) -> Result {
+        // Names and values are sent as given, short of breaking the header
+        if cookies
+            .iter()
+            .any(|c| HeaderValue::from_str(&c.to_string()).is_err())
+        {
+            return Err(bad_request());
+        }
+
+        let mut res = response();
+        if self.redirect {
+            res = res
+                .status(StatusCode::FOUND)
+                .typed_header(Location::from(Uri::from_static("/cookies")));
+        }
+        if !cookies.is_empty() {
+            res = res.typed_header(SetCookie(cookies));
+        }
+        res.into()
+    }
+}
+
+pub async fn set_cookies(req: Request) -> Result {
+    let mut query = CookieQuery::parse(&req)?;
+
+    let cookies = query
+        .cookies
+        .drain(..)
+        .map(|(name, value)| {
+            let mut cookie = HTTPCookie::new(name, value);
+            query.attributes.apply(&mut cookie);
+            cookie
+        })
+        .collect();
+
+    query.respond(cookies)
+}
+
+/// Expire the named cookies, which only takes when `Path` and `Domain` match
+/// the ones they were set with
+pub async fn delete_cookies(req: Request) -> Result {
+    let mut query = CookieQuery::parse(&req)?;
+
+    let cookies = query
+        .cookies
+        .drain(..)
+        .map(|(name, _)| {
+            let mut cookie = HTTPCookie::new(name, "");
+            query.attributes.apply(&mut cookie);
+            cookie.make_removal();
+            cookie
+        })
+        .collect();
+
+    query.respond(cookies)
 }
 
 #[cfg(test)]
@@ -74,6 +253,26 @@ mod test {
         assert_eq!(body, "first = value\nsecond = another");
     }
 
+    #[tokio::test]
+    async fn test_cookies_json() {
+        let res = request()
+            .header("accept", "application/json")
+            .typed_header(Cookie(vec![
+                HTTPCookie::new("first", "value"),
+                HTTPCookie::new("second", "another"),
+            ]))
+            .handle(cookies)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(
+            body,
+            json!({ "cookies": { "first": "value", "second": "another" } })
+        );
+    }
+
     #[tokio::test]
     async fn test_set_cookies() {
         let res = request()
@@ -85,7 +284,86 @@ mod test {
         assert_eq!(res.status(), StatusCode::OK);
         assert_eq!(
             res.headers().typed_get::<SetCookie>().unwrap(),
-            SetCookie(HTTPCookie::new("test", "value"))
+            SetCookie(vec![HTTPCookie::new("test", "value")])
         )
     }
+
+    fn set_cookie_lines(res: &hyper::Response<crate::http::Body>) -> Vec<&str> {
+        let values = res.headers().get_all(SetCookie::name()).iter();
+        values.map(|v| v.to_str().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_set_multiple_cookies_with_attributes() {
+        let res = request()
+            .path(
+                "/?first=value&second=another&Path=/&Max-Age=60&Secure\
+                 &httponly=true&SameSite=lax&Partitioned\
+                 &Expires=Wed,%2021%20Oct%202015%2007:28:00%20GMT",
+            )
+            .handle(set_cookies)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let attributes = "HttpOnly; SameSite=Lax; Partitioned; Secure; \
+                          Path=/; Max-Age=60; \
+                          Expires=Wed, 21 Oct 2015 07:28:00 GMT";
+        assert_eq!(
+            set_cookie_lines(&res),
+            [
+                format!("first=value; {attributes}"),
+                format!("second=another; {attributes}"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_cookies_invalid_attribute() {
+        for query in ["SameSite=sometimes", "Max-Age=soon", "Secure=yes"] {
+            let res = request()
+                .path(&format!("/?test=value&{query}"))
+                .handle(set_cookies)
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_cookies_redirect() {
+        let res = request()
+            .path("/?test=value&redirect")
+            .handle(set_cookies)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::FOUND);
+        assert_eq!(res.headers()["location"], "/cookies");
+        assert_eq!(set_cookie_lines(&res), ["test=value"]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_cookies() {
+        let res = request()
+            .path("/?first&second&path=/app")
+            .handle(delete_cookies)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookies = res.headers().typed_get::<SetCookie>().unwrap();
+        assert_eq!(
+            cookies.iter().map(|c| c.name()).collect::<Vec<_>>(),
+            ["first", "second"]
+        );
+        for cookie in cookies.iter() {
+            assert_eq!(cookie.value(), "");
+            assert_eq!(cookie.path(), Some("/app"));
+            assert_eq!(cookie.max_age(), Some(Duration::ZERO));
+            let expires = cookie.expires_datetime().unwrap();
+            assert!(expires < OffsetDateTime::now_utc());
+        }
+    }
 }
@@ -0,0 +1,166 @@
+use crate::http::{Request, Result, StatusCode, bad_request, response};
+use hyper::Method;
+use hyper::header::{self, HeaderName, HeaderValue};
+use serde_derive::Deserialize;
+
+/// Value of `allow_origin` that echoes the request's `Origin`
+const REFLECT: &str = "reflect";
+
+/// Every field is sent verbatim so clients can exercise broken responses
+#[derive(Deserialize)]
+pub struct CorsParams {
+    allow_origin: Option<String>,
+    allow_methods: Option<String>,
+    allow_headers: Option<String>,
+    allow_credentials: Option<String>,
+    allow_private_network: Option<String>,
+    expose_headers: Option<String>,
+    max_age: Option<String>,
+    status: Option<u16>,
+}
+
+impl CorsParams {
+    fn headers<'a>(
+        &'a self,
+        req: &'a Request,
+    ) -> impl Iterator<Item = (HeaderName, &'a str)> {
+        let allow_origin = match self.allow_origin.as_deref() {
+            Some(REFLECT) => req
+                .headers()
+                .get(header::ORIGIN)
+                .and_then(|origin| origin.to_str().ok()),
+            origin => origin,
+        };
+
+        [
+            (header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin),
+            (
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                self.allow_methods.as_deref(),
+            ),
+            (
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                self.allow_headers.as_deref(),
+            ),
+            (
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                self.allow_credentials.as_deref(),
+            ),
+            (
+                HeaderName::from_static("access-control-allow-private-network"),
+                self.allow_private_network.as_deref(),
+            ),
+            (
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                self.expose_headers.as_deref(),
+            ),
+            (header::ACCESS_CONTROL_MAX_AGE, self.max_age.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+    }
+}
+
+pub async fn cors(req: Request) -> Result {
+    let query = req.query::<CorsParams>().map_err(|_| bad_request())?;
+
+    let status = match query.status {
+        Some(code) => StatusCode::from_u16(code).map_err(|_| bad_request())?,
+        None if req.method() == Method::OPTIONS => StatusCode::NO_CONTENT,
+        None => StatusCode::OK,
+    };
+
+    let mut res = response().status(status);
+    for (name, value) in query.headers(&req) {
+        let value = HeaderValue::from_str(value).map_err(|_| bad_request())?;
+        res = res.header(name, value);
+    }
+    res.into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+
+    #[tokio::test]
+    async fn test_cors_no_params() {
+        let res = request().handle(cors).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(
+            !res.headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        let res = request()
+            .method(Method::OPTIONS)
+            .path(
+                "/?allow_origin=http://example.com&allow_methods=PUT,DELETE\
+                 &allow_credentials=true&max_age=600",
+            )
+            .handle(cors)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let headers = res.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "PUT,DELETE");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+    }
+
+    #[tokio::test]
+    async fn test_cors_reflect_origin() {
+        let res = request()
+            .path("/?allow_origin=reflect&expose_headers=X-Custom")
+            .header(header::ORIGIN, "http://example.com")
+            .handle(cors)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://example.com"
+        );
+        assert_eq!(
+            res.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+            "X-Custom"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cors_broken_values() {
+        let res = request()
+            .method(Method::OPTIONS)
+            .path("/?allow_origin=*&allow_credentials=yes&status=500")
+            .handle(cors)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(res.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(
+            res.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+            "yes"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cors_invalid_header_value() {
+        let res = request()
+            .path("/?allow_origin=a%0Ab")
+            .handle(cors)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
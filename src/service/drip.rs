@@ -0,0 +1,129 @@
+use crate::headers::{ContentLength, ContentType};
+use crate::http::{
+    Bytes, Request, Result, StatusCode, bad_request, body_from_stream, response,
+};
+use futures::prelude::*;
+use futures_timer::Delay;
+use serde_derive::Deserialize;
+use std::cmp::min;
+use std::time::Duration;
+
+const MAX_BYTES: u64 = 10 * 1024;
+const MAX_SECONDS: f64 = 10.0;
+const DEFAULT_DURATION: f64 = 2.0;
+const DEFAULT_DELAY: f64 = 0.0;
+
+#[derive(Deserialize)]
+pub struct DripParams {
+    numbytes: Option<u64>,
+    duration: Option<f64>,
+    delay: Option<f64>,
+    code: Option<u16>,
+}
+
+fn seconds(value: f64) -> Option<Duration> {
+    // `min` would turn NaN into the maximum
+    if value.is_nan() {
+        return None;
+    }
+    Duration::try_from_secs_f64(value.min(MAX_SECONDS)).ok()
+}
+
+pub async fn drip(req: Request) -> Result {
+    let query = req.query::<DripParams>().map_err(|_| bad_request())?;
+
+    let numbytes = min(query.numbytes.unwrap_or(10), MAX_BYTES);
+    let duration = seconds(query.duration.unwrap_or(DEFAULT_DURATION))
+        .ok_or_else(bad_request)?;
+    let delay = seconds(query.delay.unwrap_or(DEFAULT_DELAY))
+        .ok_or_else(bad_request)?;
+    // hyper turns a final 1xx into a 500
+    let status = StatusCode::from_u16(query.code.unwrap_or(200))
+        .ok()
+        .filter(|status| !status.is_informational())
+        .ok_or_else(bad_request)?;
+
+    let _ = Delay::new(delay).await;
+
+    // Pace each byte so the last one arrives once `duration` has elapsed
+    let interval = match numbytes {
+        0 => Duration::ZERO,
+        n => duration / n as u32,
+    };
+    let bytes = stream::iter(0..numbytes).then(move |_| async move {
+        let _ = Delay::new(interval).await;
+        Bytes::from_static(b"*")
+    });
+
+    response()
+        .status(status)
+        .typed_header(ContentType::octet_stream())
+        .typed_header(ContentLength(numbytes))
+        .body(body_from_stream(Box::pin(bytes)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+
+    #[tokio::test]
+    async fn test_drip() {
+        let res = request()
+            .path("/?numbytes=5&duration=0.01&delay=0&code=201")
+            .handle(drip)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers()["content-length"], "5");
+        assert_eq!(res.read_body_utf8().await.unwrap(), "*****");
+    }
+
+    #[tokio::test]
+    async fn test_drip_capped() {
+        let res = request()
+            .path("/?numbytes=100000&duration=0&delay=0")
+            .handle(drip)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.read_body().await.unwrap().len() as u64, MAX_BYTES);
+    }
+
+    #[tokio::test]
+    async fn test_drip_no_delay_by_default() {
+        let started = std::time::Instant::now();
+        let res = request()
+            .path("/?numbytes=1&duration=0")
+            .handle(drip)
+            .await
+            .unwrap();
+
+        assert_eq!(res.read_body_utf8().await.unwrap(), "*");
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_drip_invalid_duration() {
+        for path in ["/?duration=-1", "/?duration=NaN", "/?delay=nan"] {
+            let res = request().path(path).handle(drip).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drip_invalid_code() {
+        for code in ["1000", "100", "103"] {
+            let res = request()
+                .path(&format!("/?delay=0&code={code}"))
+                .handle(drip)
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{code}");
+        }
+    }
+}
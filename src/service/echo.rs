@@ -0,0 +1,126 @@
+use crate::headers::XForwardedFor;
+use crate::http::Request;
+use crate::service::redirect::uri::absolute_url;
+use itertools::Itertools;
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+
+/// Title-case a header name the way httpbin reports it (`User-Agent`)
+fn title_case(name: &str) -> String {
+    name.split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .join("-")
+}
+
+/// Collect pairs into an object, turning repeated keys into arrays
+pub fn multi_map<V: Into<Value>>(
+    pairs: impl IntoIterator<Item = (String, V)>,
+) -> Value {
+    let mut map = Map::new();
+    for (key, value) in pairs {
+        let value = value.into();
+        match map.get_mut(&key) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Value::Array(vec![first, value]);
+            }
+            None => {
+                map.insert(key, value);
+            }
+        }
+    }
+    Value::Object(map)
+}
+
+pub fn origin(req: &Request) -> Option<IpAddr> {
+    req.typed_header::<XForwardedFor>()
+        .map(|header| header.ip_addr())
+        .or_else(|| Some(req.client_addr()?.ip()))
+}
+
+pub fn args(req: &Request) -> anyhow::Result<Value> {
+    Ok(multi_map(req.query::<Vec<(String, String)>>()?))
+}
+
+pub fn headers(req: &Request) -> anyhow::Result<Value> {
+    let mut map = Map::new();
+    for name in req.headers().keys() {
+        let values = req
+            .headers()
+            .get_all(name)
+            .iter()
+            .map(|value| value.to_str().map(str::trim))
+            .collect::<Result<Vec<_>, _>>()?;
+        map.insert(title_case(name.as_str()), Value::String(values.join(",")));
+    }
+    Ok(Value::Object(map))
+}
+
+pub fn url(req: &Request) -> String {
+    absolute_url(req, req.uri())
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| req.uri().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_title_case() {
+        assert_eq!(title_case("user-agent"), "User-Agent");
+        assert_eq!(title_case("x-request-id"), "X-Request-Id");
+        assert_eq!(title_case("host"), "Host");
+    }
+
+    #[test]
+    fn test_multi_map() {
+        let pairs = vec![
+            (String::from("a"), String::from("1")),
+            (String::from("b"), String::from("2")),
+            (String::from("a"), String::from("3")),
+            (String::from("a"), String::from("4")),
+        ];
+        assert_eq!(multi_map(pairs), json!({"a": ["1", "3", "4"], "b": "2"}));
+    }
+
+    #[test]
+    fn test_headers() {
+        let req = request()
+            .header("x-test", "one")
+            .header("x-test", "two")
+            .header("user-agent", "ExampleBot")
+            .build();
+
+        assert_eq!(
+            headers(&req).unwrap(),
+            json!({"X-Test": "one,two", "User-Agent": "ExampleBot"})
+        );
+    }
+
+    #[test]
+    fn test_url() {
+        let req = request()
+            .path("/get?a=1")
+            .header("host", "example.com")
+            .build();
+
+        assert_eq!(url(&req), "http://example.com/get?a=1");
+    }
+
+    #[test]
+    fn test_url_no_host() {
+        let req = request().path("/get?a=1").build();
+
+        assert_eq!(url(&req), "/get?a=1");
+    }
+}
@@ -1,8 +1,15 @@
-use crate::http::{Request, Result, bad_request, ok, response};
+use crate::http::{Format, Request, Result, bad_request, json, ok, response};
+use crate::service::echo;
 use hyper::header::{HeaderName, HeaderValue};
 use itertools::{Itertools, process_results};
+use serde_json::json;
 
 pub async fn headers(req: Request) -> Result {
+    if req.format() == Format::Json {
+        let headers = echo::headers(&req).map_err(|_| bad_request())?;
+        return json(&json!({ "headers": headers }));
+    }
+
     let request_headers = req
         .headers()
         .iter()
@@ -74,6 +81,28 @@ mod test {
         assert!(body.contains("warning: 299 Another warning"));
     }
 
+    #[tokio::test]
+    async fn test_headers_json() {
+        let res = request()
+            .header("X-Request-ID", "1234")
+            .header("Accept", "application/json")
+            .handle(headers)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "headers": {
+                    "X-Request-Id": "1234",
+                    "Accept": "application/json",
+                }
+            })
+        );
+    }
+
     #[tokio::test]
     async fn test_response_headers() {
         let res = request()
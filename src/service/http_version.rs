@@ -0,0 +1,35 @@
+use crate::http::{Request, Result, ok};
+
+pub async fn http_version(req: Request) -> Result {
+    ok(format!("{:?}", req.version()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use hyper::Version;
+    use hyper::http::StatusCode;
+
+    #[tokio::test]
+    async fn test_http_version() {
+        let res = request().handle(http_version).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(body, "HTTP/1.1");
+    }
+
+    #[tokio::test]
+    async fn test_http_version_h2() {
+        let res = request()
+            .version(Version::HTTP_2)
+            .handle(http_version)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(body, "HTTP/2.0");
+    }
+}
@@ -1,14 +1,14 @@
-use crate::headers::XForwardedFor;
-use crate::http::{Request, Result, bad_request, ok};
+use crate::http::{Format, Request, Result, bad_request, json, ok};
+use crate::service::echo::origin;
+use serde_json::json;
 
 pub async fn ip(req: Request) -> Result {
-    let ip = req
-        .typed_header::<XForwardedFor>()
-        .map(|header| header.ip_addr())
-        .or_else(|| Some(req.client_addr()?.ip()))
-        .ok_or_else(bad_request)?;
+    let ip = origin(&req).ok_or_else(bad_request)?;
 
-    ok(format!("{ip}"))
+    match req.format() {
+        Format::Text => ok(format!("{ip}")),
+        Format::Json => json(&json!({ "origin": ip })),
+    }
 }
 
 #[cfg(test)]
@@ -44,6 +44,20 @@ mod test {
         assert_eq!(body, "127.0.0.1");
     }
 
+    #[tokio::test]
+    async fn test_ip_json() {
+        let res = request()
+            .header("accept", "application/json")
+            .client_addr("127.0.0.1:1234".parse().unwrap())
+            .handle(ip)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body, json!({ "origin": "127.0.0.1" }));
+    }
+
     #[tokio::test]
     async fn test_ip_missing_addr() {
         let res = request().handle(ip).await.unwrap();
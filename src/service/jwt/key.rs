@@ -0,0 +1,177 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, errors,
+};
+use ring::rand::SystemRandom;
+use ring::signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair};
+use rsa::RsaPrivateKey;
+use rsa::pkcs1::EncodeRsaPrivateKey;
+use rsa::traits::PublicKeyParts;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+const RSA_BITS: usize = 2048;
+
+fn kid() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// A key signing JWTs, asymmetric ones are generated on the fly and
+/// published as a JWK
+pub struct SigningKey {
+    algorithm: Algorithm,
+    kid: Option<String>,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    jwk: Option<Value>,
+}
+
+impl SigningKey {
+    /// An HS256 key from a shared secret
+    pub fn hmac(secret: &[u8]) -> Self {
+        SigningKey {
+            algorithm: Algorithm::HS256,
+            kid: None,
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    /// A fresh ES256 key pair
+    pub fn ecdsa() -> Self {
+        let rng = SystemRandom::new();
+        let alg = &ECDSA_P256_SHA256_FIXED_SIGNING;
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(alg, &rng)
+            .expect("failed to generate signing key");
+        let key_pair = EcdsaKeyPair::from_pkcs8(alg, pkcs8.as_ref(), &rng)
+            .expect("failed to load signing key");
+
+        // An uncompressed point, a 0x04 tag followed by both coordinates
+        let (x, y) = key_pair.public_key().as_ref()[1..].split_at(32);
+        let x = URL_SAFE_NO_PAD.encode(x);
+        let y = URL_SAFE_NO_PAD.encode(y);
+
+        let kid = kid();
+        let jwk = json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": x,
+            "y": y,
+            "use": "sig",
+            "alg": "ES256",
+            "kid": kid,
+        });
+
+        SigningKey {
+            algorithm: Algorithm::ES256,
+            encoding: EncodingKey::from_ec_der(pkcs8.as_ref()),
+            decoding: DecodingKey::from_ec_components(&x, &y)
+                .expect("invalid public key"),
+            kid: Some(kid),
+            jwk: Some(jwk),
+        }
+    }
+
+    /// A fresh RS256 key pair
+    pub fn rsa() -> Self {
+        let key = RsaPrivateKey::new(&mut rsa::rand_core::OsRng, RSA_BITS)
+            .expect("failed to generate signing key");
+        let der = key.to_pkcs1_der().expect("failed to encode signing key");
+
+        let n = URL_SAFE_NO_PAD.encode(key.n().to_bytes_be());
+        let e = URL_SAFE_NO_PAD.encode(key.e().to_bytes_be());
+
+        let kid = kid();
+        let jwk = json!({
+            "kty": "RSA",
+            "n": n,
+            "e": e,
+            "use": "sig",
+            "alg": "RS256",
+            "kid": kid,
+        });
+
+        SigningKey {
+            algorithm: Algorithm::RS256,
+            encoding: EncodingKey::from_rsa_der(der.as_bytes()),
+            decoding: DecodingKey::from_rsa_components(&n, &e)
+                .expect("invalid public key"),
+            kid: Some(kid),
+            jwk: Some(jwk),
+        }
+    }
+
+    pub fn jwk(&self) -> Option<&Value> {
+        self.jwk.as_ref()
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> String {
+        let header = Header {
+            kid: self.kid.clone(),
+            ..Header::new(self.algorithm)
+        };
+        jsonwebtoken::encode(&header, claims, &self.encoding)
+            .expect("failed to sign token")
+    }
+
+    /// Checks of the signature and expiry, for callers to extend
+    pub fn validation(&self) -> Validation {
+        let mut validation = Validation::new(self.algorithm);
+        validation.leeway = 0;
+        validation.validate_aud = false;
+        validation
+    }
+
+    pub fn verify<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        validation: &Validation,
+    ) -> errors::Result<T> {
+        jsonwebtoken::decode(token, &self.decoding, validation)
+            .map(|data| data.claims)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SigningKey;
+    use jsonwebtoken::errors::ErrorKind;
+    use serde_json::{Value, json};
+
+    fn round_trip(key: &SigningKey) {
+        let claims = json!({ "sub": "user", "exp": u32::MAX });
+        let token = key.sign(&claims);
+
+        let verified = key.verify::<Value>(&token, &key.validation());
+        assert_eq!(verified.unwrap(), claims);
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        round_trip(&SigningKey::hmac(b"secret"));
+        round_trip(&SigningKey::ecdsa());
+        round_trip(&SigningKey::rsa());
+    }
+
+    #[test]
+    fn test_other_key() {
+        let key = SigningKey::ecdsa();
+        let token = key.sign(&json!({ "sub": "user", "exp": u32::MAX }));
+
+        let other = SigningKey::ecdsa();
+        let err = other.verify::<Value>(&token, &other.validation());
+        assert_eq!(err.unwrap_err().kind(), &ErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn test_expired() {
+        let key = SigningKey::hmac(b"secret");
+        let token = key.sign(&json!({ "sub": "user", "exp": 1 }));
+
+        let err = key.verify::<Value>(&token, &key.validation());
+        assert_eq!(err.unwrap_err().kind(), &ErrorKind::ExpiredSignature);
+    }
+}
@@ -0,0 +1,338 @@
+pub(super) mod key;
+
+use self::key::SigningKey;
+use super::redirect::uri::absolute_url;
+use crate::headers::authorization::Bearer;
+use crate::headers::{Authorization, Challenge, WWWAuthenticate};
+use crate::http::{
+    Error, Request, Result, StatusCode, Uri, bad_request, json, not_found,
+    response,
+};
+use jsonwebtoken::errors::{self, ErrorKind};
+use jsonwebtoken::get_current_timestamp;
+use serde_derive::Deserialize;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+const DEFAULT_SUBJECT: &str = "user";
+const DEFAULT_EXPIRES_IN: i64 = 60 * 60;
+
+/// Issuer of the tokens signed here, the server's base URL
+#[allow(clippy::result_large_err)]
+pub(super) fn issuer(req: &Request) -> std::result::Result<String, Error> {
+    let url =
+        absolute_url(req, &Uri::from_static("/")).map_err(|_| bad_request())?;
+    Ok(url.as_str().trim_end_matches('/').to_owned())
+}
+
+/// A Bearer challenge, with an `invalid_token` error when one was presented
+/// (RFC 6750 3.1)
+pub(super) fn bearer_challenge(description: Option<&str>) -> Error {
+    let mut challenge = Challenge::new("Bearer");
+    if let Some(description) = description {
+        challenge = challenge
+            .param("error", "invalid_token")
+            .param("error_description", description);
+    }
+    response()
+        .status(StatusCode::UNAUTHORIZED)
+        .typed_header(WWWAuthenticate::from(challenge))
+        .into()
+}
+
+/// The `error_description` explaining why a token was rejected
+pub(super) fn describe(err: &errors::Error) -> String {
+    let description = match err.kind() {
+        ErrorKind::ExpiredSignature => "the token has expired",
+        ErrorKind::ImmatureSignature => "the token is not valid yet",
+        ErrorKind::InvalidIssuer => "the token has another issuer",
+        ErrorKind::InvalidAudience => "the token has another audience",
+        ErrorKind::InvalidSignature => "the signature is invalid",
+        ErrorKind::InvalidAlgorithm => {
+            "the token is signed with another algorithm"
+        }
+        ErrorKind::MissingRequiredClaim(claim) => {
+            return format!("the token has no {claim} claim");
+        }
+        _ => "the token is malformed",
+    };
+    description.to_owned()
+}
+
+#[derive(Deserialize)]
+pub struct MintQuery {
+    sub: Option<String>,
+    iss: Option<String>,
+    aud: Option<String>,
+    /// Seconds until `exp`, negative for an expired token
+    expires_in: Option<i64>,
+    /// Seconds until `nbf`
+    not_before_in: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ValidateQuery {
+    iss: Option<String>,
+    aud: Option<String>,
+}
+
+struct JwtState {
+    hs256: SigningKey,
+    es256: Arc<SigningKey>,
+    rs256: OnceCell<SigningKey>,
+}
+
+/// Mints and validates tokens signed with HS256, RS256 or ES256
+#[derive(Clone)]
+pub struct Jwt(Arc<JwtState>);
+
+impl Jwt {
+    pub fn new(secret: &[u8], es256: Arc<SigningKey>) -> Self {
+        Jwt(Arc::new(JwtState {
+            hs256: SigningKey::hmac(secret),
+            es256,
+            rs256: OnceCell::new(),
+        }))
+    }
+
+    async fn rs256(&self) -> &SigningKey {
+        // Generating RSA keys is slow, so wait until one is needed and keep
+        // it off the async workers
+        self.0
+            .rs256
+            .get_or_init(|| async {
+                tokio::task::spawn_blocking(SigningKey::rsa)
+                    .await
+                    .expect("failed to generate signing key")
+            })
+            .await
+    }
+
+    async fn key(
+        &self,
+        alg: Option<&str>,
+    ) -> std::result::Result<&SigningKey, Error> {
+        match alg {
+            Some("HS256") => Ok(&self.0.hs256),
+            Some("RS256") => Ok(self.rs256().await),
+            Some("ES256") => Ok(&self.0.es256),
+            _ => Err(not_found()),
+        }
+    }
+
+    pub async fn mint(self, req: Request) -> Result {
+        let key = self.key(req.param::<String>("alg").as_deref()).await?;
+        let query = req.query::<MintQuery>().map_err(|_| bad_request())?;
+
+        let now = get_current_timestamp();
+        let iss = match query.iss {
+            Some(iss) => iss,
+            None => issuer(&req)?,
+        };
+        let expires_in = query.expires_in.unwrap_or(DEFAULT_EXPIRES_IN);
+
+        let mut claims = json!({
+            "iss": iss,
+            "sub": query.sub.as_deref().unwrap_or(DEFAULT_SUBJECT),
+            "iat": now,
+            "exp": now.saturating_add_signed(expires_in),
+        });
+        if let Some(aud) = query.aud {
+            claims["aud"] = aud.into();
+        }
+        if let Some(not_before_in) = query.not_before_in {
+            claims["nbf"] = now.saturating_add_signed(not_before_in).into();
+        }
+
+        json(&json!({
+            "token": key.sign(&claims),
+            "claims": claims,
+        }))
+    }
+
+    /// Validate a Bearer token, expecting this server as the issuer unless
+    /// another `iss` is given
+    pub async fn validate(self, req: Request) -> Result {
+        let key = self.key(req.param::<String>("alg").as_deref()).await?;
+        let query = req.query::<ValidateQuery>().map_err(|_| bad_request())?;
+        let bearer = req
+            .typed_header::<Authorization<Bearer>>()
+            .ok_or_else(|| bearer_challenge(None))?;
+        let token = bearer.token();
+
+        let iss = match query.iss {
+            Some(iss) => iss,
+            None => issuer(&req)?,
+        };
+        let mut validation = key.validation();
+        validation.validate_nbf = true;
+        validation.validate_aud = true;
+        validation.set_issuer(&[&iss]);
+        validation.set_required_spec_claims(&["exp", "iss"]);
+        if let Some(aud) = &query.aud {
+            validation.set_audience(&[aud]);
+            validation.required_spec_claims.insert(String::from("aud"));
+        }
+
+        let rejected = |err: errors::Error| {
+            let description = match (err.kind(), &query.aud) {
+                (ErrorKind::InvalidIssuer, _) => {
+                    format!("the token was not issued by {iss}")
+                }
+                (ErrorKind::InvalidAudience, Some(aud)) => {
+                    format!("the token is not intended for {aud}")
+                }
+                (ErrorKind::InvalidAudience, None) => String::from(
+                    "the token has an audience, which must be given as aud",
+                ),
+                _ => describe(&err),
+            };
+            bearer_challenge(Some(&description))
+        };
+        let header = jsonwebtoken::decode_header(token).map_err(rejected)?;
+        let claims =
+            key.verify::<Value>(token, &validation).map_err(rejected)?;
+
+        json(&json!({
+            "header": header,
+            "claims": claims,
+        }))
+    }
+
+    pub async fn jwks(self, _: Request) -> Result {
+        let keys = [self.0.es256.jwk(), self.rs256().await.jwk()];
+        json(&json!({ "keys": keys.into_iter().flatten().collect::<Vec<_>>() }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::handler::with_state;
+    use crate::test::*;
+    use hyper::header::WWW_AUTHENTICATE;
+
+    fn jwt() -> Jwt {
+        Jwt::new(b"secret", Arc::new(SigningKey::ecdsa()))
+    }
+
+    async fn mint(jwt: &Jwt, alg: &str, query: &str) -> String {
+        let res = request()
+            .param("alg", alg)
+            .path(&format!("/?{query}"))
+            .header("host", "example.com")
+            .handle(with_state(jwt.clone(), Jwt::mint))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        body["token"].as_str().unwrap().to_owned()
+    }
+
+    async fn validate(
+        jwt: &Jwt,
+        alg: &str,
+        query: &str,
+        token: &str,
+    ) -> hyper::Response<crate::http::Body> {
+        request()
+            .param("alg", alg)
+            .path(&format!("/?{query}"))
+            .header("host", "example.com")
+            .typed_header(Authorization::bearer(token).unwrap())
+            .handle(with_state(jwt.clone(), Jwt::validate))
+            .await
+            .unwrap()
+    }
+
+    fn error_description(res: &hyper::Response<crate::http::Body>) -> String {
+        let header = res.headers()[WWW_AUTHENTICATE].to_str().unwrap();
+        let challenge = header.parse::<Challenge>().unwrap();
+        assert_eq!(challenge.get("error"), Some("invalid_token"));
+        challenge.get("error_description").unwrap().to_owned()
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let jwt = jwt();
+        for alg in ["HS256", "RS256", "ES256"] {
+            let token = mint(&jwt, alg, "sub=alice&aud=api").await;
+            let res = validate(&jwt, alg, "aud=api", &token).await;
+
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.read_body_json().await.unwrap();
+            assert_eq!(body["header"]["alg"], alg);
+            assert_eq!(body["claims"]["sub"], "alice");
+            assert_eq!(body["claims"]["iss"], "http://example.com");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_algorithm() {
+        let res = request()
+            .param("alg", "none")
+            .handle(with_state(jwt(), Jwt::mint))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_missing_token() {
+        let res = request()
+            .param("alg", "HS256")
+            .handle(with_state(jwt(), Jwt::validate))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(res.headers()[WWW_AUTHENTICATE], "Bearer");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_tokens() {
+        let jwt = jwt();
+        let cases = [
+            ("expires_in=-10", "", "the token has expired"),
+            ("not_before_in=600", "", "the token is not valid yet"),
+            (
+                "iss=other",
+                "",
+                "the token was not issued by http://example.com",
+            ),
+            ("aud=api", "aud=web", "the token is not intended for web"),
+            (
+                "aud=api",
+                "",
+                "the token has an audience, which must be given as aud",
+            ),
+            ("", "aud=api", "the token has no aud claim"),
+        ];
+        for (mint_query, validate_query, expected) in cases {
+            let token = mint(&jwt, "HS256", mint_query).await;
+            let res = validate(&jwt, "HS256", validate_query, &token).await;
+
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(error_description(&res), expected, "{mint_query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wrong_key() {
+        let jwt = jwt();
+        let token = mint(&jwt, "HS256", "").await;
+
+        let res = validate(&jwt, "ES256", "", &token).await;
+        assert_eq!(
+            error_description(&res),
+            "the token is signed with another algorithm"
+        );
+
+        let other = Jwt::new(b"other", Arc::new(SigningKey::ecdsa()));
+        let res = validate(&other, "HS256", "", &token).await;
+        assert_eq!(error_description(&res), "the signature is invalid");
+    }
+}
@@ -1,23 +1,127 @@
 use crate::headers::ContentType;
-use crate::http::{Bytes, Request, Result, bad_request, ok};
+use crate::http::{
+    Bytes, Encoding, Error, Format, Request, Response, Result, StatusCode,
+    bad_request, json, json_error, ok, response,
+};
+use crate::service::echo;
+use crate::service::method::multipart::{FilePart, parse_multipart};
+use futures::prelude::*;
 use http_body_util::BodyExt;
+use hyper::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
 use itertools::Itertools;
+use serde_json::{Value, json};
 use std::str;
 
-fn parse_url_encoded_body(raw_body: &[u8]) -> anyhow::Result<String> {
-    Ok(
-        serde_urlencoded::from_bytes::<Vec<(String, String)>>(raw_body)?
-            .iter()
-            .format_with("\n", |(key, value), f| {
-                f(&format_args!("{key} = {value}"))
-            })
-            .to_string(),
-    )
+/// Largest request body we will inflate a compressed upload to
+const MAX_DECODED_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Sizes of a request body before and after removing its content codings
+struct Decoded {
+    encodings: Vec<Encoding>,
+    original_size: usize,
+    decoded_size: usize,
+}
+
+impl Decoded {
+    fn to_json(&self) -> Value {
+        json!({
+            "content_encoding": self.encodings
+                .iter()
+                .map(|encoding| encoding.as_str())
+                .join(", "),
+            "original_size": self.original_size,
+            "decoded_size": self.decoded_size,
+        })
+    }
+
+    fn add_headers(&self, mut res: Response) -> Response {
+        let headers = res.headers_mut();
+        headers.insert("x-original-content-length", self.original_size.into());
+        headers.insert("x-decoded-content-length", self.decoded_size.into());
+        res
+    }
+}
+
+enum ParsedBody {
+    Form(Vec<(String, String)>),
+    Json {
+        text: String,
+        value: Value,
+    },
+    Multipart {
+        fields: Vec<(String, String)>,
+        files: Vec<FilePart>,
+    },
+    Raw(String),
+}
+
+impl ParsedBody {
+    fn to_text(&self) -> String {
+        match self {
+            Self::Form(pairs) => pairs
+                .iter()
+                .format_with("\n", |(key, value), f| {
+                    f(&format_args!("{key} = {value}"))
+                })
+                .to_string(),
+            Self::Json { text, .. } => text.clone(),
+            Self::Multipart { fields, files } => fields
+                .iter()
+                .map(|(key, value)| format!("{key} = {value}"))
+                .chain(files.iter().map(ToString::to_string))
+                .join("\n"),
+            Self::Raw(text) => text.clone(),
+        }
+    }
+
+    fn into_json(self, req: &Request) -> anyhow::Result<Value> {
+        let mut parsed = Value::Null;
+        let (data, form, files) = match self {
+            Self::Form(pairs) => {
+                (String::new(), echo::multi_map(pairs), json!({}))
+            }
+            Self::Json { text, value } => {
+                parsed = value;
+                (text, json!({}), json!({}))
+            }
+            Self::Multipart { fields, files } => (
+                String::new(),
+                echo::multi_map(fields),
+                echo::multi_map(
+                    files
+                        .iter()
+                        .map(|file| (file.name.clone(), file.to_json())),
+                ),
+            ),
+            Self::Raw(text) => (text, json!({}), json!({})),
+        };
+
+        Ok(json!({
+            "args": echo::args(req)?,
+            "data": data,
+            "files": files,
+            "form": form,
+            "headers": echo::headers(req)?,
+            "json": parsed,
+            "origin": echo::origin(req),
+            "url": echo::url(req),
+        }))
+    }
+}
+
+fn parse_url_encoded_body(
+    raw_body: &[u8],
+) -> anyhow::Result<Vec<(String, String)>> {
+    Ok(serde_urlencoded::from_bytes::<Vec<(String, String)>>(
+        raw_body,
+    )?)
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 enum ContentTypeDecoder {
     UrlEncoded,
+    Json,
+    Multipart(String),
     Raw,
 }
 
@@ -31,35 +135,202 @@ fn content_type_decoder(req: &Request) -> ContentTypeDecoder {
         (mime::APPLICATION, mime::WWW_FORM_URLENCODED) => {
             ContentTypeDecoder::UrlEncoded
         }
+        (mime::APPLICATION, mime::JSON) => ContentTypeDecoder::Json,
+        (mime::APPLICATION, _) if content_type.suffix() == Some(mime::JSON) => {
+            ContentTypeDecoder::Json
+        }
+        (mime::MULTIPART, mime::FORM_DATA) => {
+            match content_type.get_param(mime::BOUNDARY) {
+                Some(boundary) => {
+                    ContentTypeDecoder::Multipart(boundary.to_string())
+                }
+                None => ContentTypeDecoder::Raw,
+            }
+        }
         _ => ContentTypeDecoder::Raw,
     }
 }
 
-fn parse_body(req: &Request, chunk: &Bytes) -> anyhow::Result<String> {
-    match content_type_decoder(req) {
-        ContentTypeDecoder::UrlEncoded => Ok(parse_url_encoded_body(chunk)?),
-        ContentTypeDecoder::Raw => Ok(str::from_utf8(&chunk[..])?.to_string()),
+/// The codings applied to the request body, in the order they were applied
+#[allow(clippy::result_large_err)]
+fn content_encodings(
+    req: &Request,
+) -> std::result::Result<Vec<Encoding>, Error> {
+    let unsupported = || -> Error {
+        let supported = Encoding::ALL.iter().map(|e| e.as_str()).join(", ");
+        response()
+            .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+            .header(ACCEPT_ENCODING, supported)
+            .into()
+    };
+
+    let mut encodings = vec![];
+    for value in req.headers().get_all(CONTENT_ENCODING) {
+        let value = value.to_str().map_err(|_| unsupported())?;
+        for coding in value.split(',').map(str::trim) {
+            if coding.is_empty() || coding.eq_ignore_ascii_case("identity") {
+                continue;
+            }
+            encodings.push(coding.parse().map_err(|_| unsupported())?);
+        }
     }
+    Ok(encodings)
 }
 
-pub async fn body(mut req: Request) -> Result {
+async fn decode_body(
+    encodings: &[Encoding],
+    mut body: Bytes,
+) -> std::result::Result<Bytes, Error> {
+    // Codings are listed in the order applied, so undo them in reverse
+    for encoding in encodings.iter().rev() {
+        body = encoding
+            .decode(&body, MAX_DECODED_SIZE)
+            .await
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::FileTooLarge => {
+                    response().status(StatusCode::PAYLOAD_TOO_LARGE).into()
+                }
+                _ => bad_request(),
+            })?
+            .into();
+    }
+    Ok(body)
+}
+
+/// A 400 pointing at where the JSON body stopped parsing
+fn invalid_json(err: &serde_json::Error) -> Error {
+    let body = json!({
+        "error": "invalid JSON",
+        "message": err.to_string(),
+        "line": err.line(),
+        "column": err.column(),
+    });
+    json_error(StatusCode::BAD_REQUEST, &body)
+}
+
+async fn parse_body(
+    decoder: ContentTypeDecoder,
+    chunk: Bytes,
+) -> anyhow::Result<ParsedBody> {
+    match decoder {
+        ContentTypeDecoder::UrlEncoded => {
+            Ok(ParsedBody::Form(parse_url_encoded_body(&chunk)?))
+        }
+        ContentTypeDecoder::Json => {
+            let text = str::from_utf8(&chunk[..])?.to_string();
+            // Bodiless requests often still send the header
+            let value = match text.trim() {
+                "" => Value::Null,
+                _ => serde_json::from_str(&text)?,
+            };
+            Ok(ParsedBody::Json { text, value })
+        }
+        ContentTypeDecoder::Multipart(boundary) => {
+            let chunks = stream::iter([Ok::<_, std::io::Error>(chunk)]);
+            let (fields, files) = parse_multipart(chunks, boundary).await?;
+            Ok(ParsedBody::Multipart { fields, files })
+        }
+        ContentTypeDecoder::Raw => {
+            Ok(ParsedBody::Raw(str::from_utf8(&chunk[..])?.to_string()))
+        }
+    }
+}
+
+async fn read_body(
+    req: &mut Request,
+) -> std::result::Result<(ParsedBody, Option<Decoded>), Error> {
+    let encodings = content_encodings(req)?;
+    let decoder = content_type_decoder(req);
+
+    // Uploads that need no decoding are parsed as they stream in
+    if let (ContentTypeDecoder::Multipart(boundary), true) =
+        (&decoder, encodings.is_empty())
+    {
+        let chunks = std::mem::take(req.body_mut()).into_data_stream();
+        let (fields, files) = parse_multipart(chunks, boundary.clone())
+            .await
+            .map_err(|_| bad_request())?;
+        return Ok((ParsedBody::Multipart { fields, files }, None));
+    }
+
     let body = req
         .body_mut()
         .collect()
         .await
         .map_err(|_| bad_request())?
         .to_bytes();
-    let content = parse_body(&req, &body).map_err(|_| bad_request())?;
-    ok(content)
+
+    let original_size = body.len();
+    let body = decode_body(&encodings, body).await?;
+    let decoded = (!encodings.is_empty()).then(|| Decoded {
+        encodings,
+        original_size,
+        decoded_size: body.len(),
+    });
+
+    let content =
+        parse_body(decoder, body).await.map_err(|err| match err
+            .downcast_ref::<serde_json::Error>(
+        ) {
+            Some(err) => invalid_json(err),
+            None => bad_request(),
+        })?;
+    Ok((content, decoded))
+}
+
+#[allow(clippy::result_large_err)]
+fn with_decoded(res: Result, decoded: Option<Decoded>) -> Result {
+    match decoded {
+        Some(decoded) => res.map(|res| decoded.add_headers(res)),
+        None => res,
+    }
+}
+
+pub async fn body(mut req: Request) -> Result {
+    let (content, decoded) = read_body(&mut req).await?;
+
+    let res = match req.format() {
+        Format::Text => ok(content.to_text()),
+        Format::Json => {
+            let mut value =
+                content.into_json(&req).map_err(|_| bad_request())?;
+            if let Some(decoded) = &decoded {
+                value["decoded"] = decoded.to_json();
+            }
+            json(&value)
+        }
+    };
+    with_decoded(res, decoded)
+}
+
+pub async fn anything(mut req: Request) -> Result {
+    let (content, decoded) = read_body(&mut req).await?;
+
+    let mut value = content.into_json(&req).map_err(|_| bad_request())?;
+    value["method"] = json!(req.method().as_str());
+    if let Some(decoded) = &decoded {
+        value["decoded"] = decoded.to_json();
+    }
+    with_decoded(json(&value), decoded)
 }
 
 #[cfg(test)]
 mod test {
     use super::{
-        ContentTypeDecoder, content_type_decoder, parse_url_encoded_body,
+        ContentTypeDecoder, anything, body, content_type_decoder,
+        parse_url_encoded_body,
     };
     use crate::headers::ContentType;
+    use crate::http::{Body, Encoding};
     use crate::test::*;
+    use http_body_util::BodyExt;
+    use hyper::StatusCode;
+    use hyper::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+
+    async fn encode(encoding: Encoding, data: &'static str) -> Vec<u8> {
+        let body = encoding.encode(Body::from(data));
+        body.collect().await.unwrap().to_bytes().to_vec()
+    }
 
     #[test]
     fn test_parse_url_encoded_body() {
@@ -68,7 +339,11 @@ mod test {
                 "first=one&second=two&third=three".as_bytes()
             )
             .unwrap(),
-            "first = one\nsecond = two\nthird = three"
+            vec![
+                (String::from("first"), String::from("one")),
+                (String::from("second"), String::from("two")),
+                (String::from("third"), String::from("three")),
+            ]
         )
     }
 
@@ -108,4 +383,222 @@ mod test {
             _ => panic!("Incorrect decoder"),
         };
     }
+
+    #[test]
+    fn test_multipart_header() {
+        let req = request()
+            .header(
+                hyper::http::header::CONTENT_TYPE,
+                "multipart/form-data; boundary=XYZ",
+            )
+            .build();
+
+        match content_type_decoder(&req) {
+            ContentTypeDecoder::Multipart(boundary) => {
+                assert_eq!(boundary, "XYZ")
+            }
+            _ => panic!("Incorrect decoder"),
+        };
+    }
+
+    #[test]
+    fn test_json_suffix_header() {
+        let req = request()
+            .header(
+                hyper::http::header::CONTENT_TYPE,
+                "application/vnd.api+json",
+            )
+            .build();
+
+        match content_type_decoder(&req) {
+            ContentTypeDecoder::Json => (),
+            _ => panic!("Incorrect decoder"),
+        };
+    }
+
+    #[tokio::test]
+    async fn test_anything_json() {
+        let res = request()
+            .method(hyper::Method::PUT)
+            .typed_header(ContentType::json())
+            .body(r#"{"a": [1, 2]}"#)
+            .handle(anything)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["json"], serde_json::json!({ "a": [1, 2] }));
+        assert_eq!(body["data"], r#"{"a": [1, 2]}"#);
+    }
+
+    #[tokio::test]
+    async fn test_anything_empty_json() {
+        for body in ["", " \n"] {
+            let res = request()
+                .method(hyper::Method::DELETE)
+                .typed_header(ContentType::json())
+                .body(body)
+                .handle(anything)
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::OK);
+            let json = res.read_body_json().await.unwrap();
+            assert_eq!(json.get("json"), Some(&serde_json::Value::Null));
+            assert_eq!(json["data"], body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_body_invalid_json() {
+        let res = request()
+            .method(hyper::Method::POST)
+            .typed_header(ContentType::json())
+            .body("{\n  \"a\": 1,\n  \"b\": x\n}")
+            .handle(body)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["line"], 3);
+        assert_eq!(body["column"], 8);
+    }
+
+    #[tokio::test]
+    async fn test_anything_multipart() {
+        let res = request()
+            .method(hyper::Method::POST)
+            .header(
+                hyper::http::header::CONTENT_TYPE,
+                "multipart/form-data; boundary=XYZ",
+            )
+            .body(
+                "--XYZ\r\n\
+                 Content-Disposition: form-data; name=\"key\"\r\n\r\n\
+                 val\r\n\
+                 --XYZ\r\n\
+                 Content-Disposition: form-data; name=\"file\"; \
+                 filename=\"empty.bin\"\r\n\r\n\
+                 \r\n\
+                 --XYZ--\r\n",
+            )
+            .handle(anything)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["form"], serde_json::json!({ "key": "val" }));
+        assert_eq!(
+            body["files"]["file"],
+            serde_json::json!({
+                "filename": "empty.bin",
+                "content_type": null,
+                "size": 0,
+                "sha256": "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_anything() {
+        let res = request()
+            .method(hyper::Method::PATCH)
+            .path("/anything/foo/bar?a=1")
+            .typed_header(ContentType::form_url_encoded())
+            .body("key=val")
+            .handle(anything)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), hyper::StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["method"], "PATCH");
+        assert_eq!(body["url"], "/anything/foo/bar?a=1");
+        assert_eq!(body["args"], serde_json::json!({ "a": "1" }));
+        assert_eq!(body["form"], serde_json::json!({ "key": "val" }));
+        assert_eq!(body["headers"]["Content-Length"], "7");
+    }
+
+    #[tokio::test]
+    async fn test_anything_raw_body() {
+        let res = request().body("raw data").handle(anything).await.unwrap();
+
+        assert_eq!(res.status(), hyper::StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["method"], "GET");
+        assert_eq!(body["data"], "raw data");
+    }
+
+    #[tokio::test]
+    async fn test_body_gzip() {
+        let data = encode(Encoding::Gzip, "compressed body").await;
+        let size = data.len().to_string();
+        let res = request()
+            .method(hyper::Method::POST)
+            .header(CONTENT_ENCODING, "gzip")
+            .body(data)
+            .handle(body)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["x-original-content-length"], size.as_str());
+        assert_eq!(res.headers()["x-decoded-content-length"], "15");
+        assert_eq!(res.read_body_utf8().await.unwrap(), "compressed body");
+    }
+
+    #[tokio::test]
+    async fn test_anything_stacked_encodings() {
+        let inner = encode(Encoding::Brotli, "key=val").await;
+        let data = Encoding::Zstd
+            .encode(Body::from(inner))
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        let res = request()
+            .method(hyper::Method::POST)
+            .typed_header(ContentType::form_url_encoded())
+            .header(CONTENT_ENCODING, "br, zstd")
+            .body(data)
+            .handle(anything)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["form"], serde_json::json!({ "key": "val" }));
+        assert_eq!(body["decoded"]["content_encoding"], "br, zstd");
+        assert_eq!(body["decoded"]["decoded_size"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_body_unknown_encoding() {
+        let res = request()
+            .method(hyper::Method::POST)
+            .header(CONTENT_ENCODING, "compress")
+            .body("data")
+            .handle(body)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(res.headers()[ACCEPT_ENCODING], "gzip, deflate, br, zstd");
+    }
+
+    #[tokio::test]
+    async fn test_body_corrupt_encoding() {
+        let res = request()
+            .method(hyper::Method::POST)
+            .header(CONTENT_ENCODING, "gzip")
+            .body("not gzip")
+            .handle(body)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
 }
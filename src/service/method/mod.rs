@@ -1,9 +1,22 @@
 mod body;
+mod multipart;
+pub use self::body::anything;
 use self::body::body;
-use crate::http::{Request, Result, bad_request, ok};
+use crate::http::{Format, Request, Result, bad_request, json, ok};
+use crate::service::echo;
 use itertools::Itertools;
+use serde_json::json;
 
 pub async fn get(req: Request) -> Result {
+    if req.format() == Format::Json {
+        return json(&json!({
+            "args": echo::args(&req).map_err(|_| bad_request())?,
+            "headers": echo::headers(&req).map_err(|_| bad_request())?,
+            "origin": echo::origin(&req),
+            "url": echo::url(&req),
+        }));
+    }
+
     let body = {
         req.query::<Vec<(String, String)>>()
             .map_err(|_| bad_request())?
@@ -70,6 +83,58 @@ mod test {
         assert_eq!(expected, result)
     }
 
+    #[tokio::test]
+    async fn test_get_json() {
+        let res = request()
+            .path("/get?key=val&key=another")
+            .header("accept", "application/json")
+            .client_addr("127.0.0.1:1234".parse().unwrap())
+            .handle(get)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "args": { "key": ["val", "another"] },
+                "headers": { "Accept": "application/json" },
+                "origin": "127.0.0.1",
+                "url": "/get?key=val&key=another",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_json_default_format() {
+        let res = request()
+            .path("/get?key=val")
+            .extension(Format::Json)
+            .handle(get)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["args"], json!({ "key": "val" }));
+    }
+
+    #[tokio::test]
+    async fn test_get_text_overrides_default_format() {
+        let res = request()
+            .path("/get?key=val")
+            .header("accept", "text/plain")
+            .extension(Format::Json)
+            .handle(get)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(body, "key = val");
+    }
+
     #[tokio::test]
     async fn test_post() {
         let res = request()
@@ -178,6 +243,43 @@ mod test {
         assert_eq!(expected, result)
     }
 
+    #[tokio::test]
+    async fn test_post_json() {
+        let res = request()
+            .method(Method::POST)
+            .path("/post?a=b")
+            .header("accept", "application/json")
+            .typed_header(ContentType::form_url_encoded())
+            .body("key=val")
+            .handle(post)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["args"], json!({ "a": "b" }));
+        assert_eq!(body["form"], json!({ "key": "val" }));
+        assert_eq!(body["data"], json!(""));
+        assert_eq!(body["files"], json!({}));
+        assert_eq!(body["json"], json!(null));
+    }
+
+    #[tokio::test]
+    async fn test_post_raw_json_format() {
+        let res = request()
+            .method(Method::POST)
+            .header("accept", "application/json")
+            .body("some text")
+            .handle(post)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["data"], json!("some text"));
+        assert_eq!(body["form"], json!({}));
+    }
+
     #[tokio::test]
     async fn test_delete() {
         let res = request()
@@ -0,0 +1,130 @@
+use crate::http::Bytes;
+use futures::prelude::*;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Metadata of an uploaded file, digested as it streams in
+pub struct FilePart {
+    pub name: String,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl FilePart {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "filename": self.filename,
+            "content_type": self.content_type,
+            "size": self.size,
+            "sha256": self.sha256,
+        })
+    }
+}
+
+impl fmt::Display for FilePart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} = {} ({}, {} bytes, sha256 {})",
+            self.name,
+            self.filename,
+            self.content_type.as_deref().unwrap_or("unknown"),
+            self.size,
+            self.sha256
+        )
+    }
+}
+
+/// Parse a `multipart/form-data` body into its fields and files
+pub async fn parse_multipart<S, E>(
+    stream: S,
+    boundary: String,
+) -> multer::Result<(Vec<(String, String)>, Vec<FilePart>)>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+{
+    let mut multipart = multer::Multipart::new(stream, boundary);
+    let mut fields = vec![];
+    let mut files = vec![];
+
+    while let Some(mut field) = multipart.next_field().await? {
+        let name = field.name().unwrap_or_default().to_owned();
+        let Some(filename) = field.file_name().map(str::to_owned) else {
+            fields.push((name, field.text().await?));
+            continue;
+        };
+
+        let content_type = field.content_type().map(ToString::to_string);
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        while let Some(chunk) = field.chunk().await? {
+            size += chunk.len() as u64;
+            hasher.update(&chunk);
+        }
+
+        files.push(FilePart {
+            name,
+            filename,
+            content_type,
+            size,
+            sha256: format!("{:x}", hasher.finalize()),
+        });
+    }
+    Ok((fields, files))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn test_parse_multipart() {
+        let body = "--XYZ\r\n\
+            Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+            hello\r\n\
+            --XYZ\r\n\
+            Content-Disposition: form-data; name=\"upload\"; filename=\"a.txt\"\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            abc\r\n\
+            --XYZ--\r\n";
+        // Split the body so the parser sees parts across chunk boundaries
+        let chunks = body
+            .as_bytes()
+            .chunks(7)
+            .map(|chunk| Ok::<_, Infallible>(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+
+        let (fields, files) =
+            parse_multipart(stream::iter(chunks), String::from("XYZ"))
+                .await
+                .unwrap();
+
+        assert_eq!(
+            fields,
+            vec![(String::from("title"), String::from("hello"))]
+        );
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "upload");
+        assert_eq!(files[0].filename, "a.txt");
+        assert_eq!(files[0].content_type.as_deref(), Some("text/plain"));
+        assert_eq!(files[0].size, 3);
+        assert_eq!(
+            files[0].sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_multipart_truncated() {
+        let body =
+            "--XYZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nb";
+        let chunks = stream::iter([Ok::<_, Infallible>(Bytes::from(body))]);
+
+        assert!(parse_multipart(chunks, String::from("XYZ")).await.is_err());
+    }
+}
@@ -1,22 +1,66 @@
+use crate::handler::with_state;
 use crate::router::{Route, Router, route};
+use crate::service::jwt::Jwt;
+use crate::service::jwt::key::SigningKey;
+use crate::service::oauth::OAuth;
+use crate::service::session::Session;
 use hyper::http::Method;
+use std::sync::Arc;
 use uri_path::path;
 
 mod auth;
 mod bytes;
 mod cache;
+mod compression;
 mod cookies;
+mod cors;
 mod delay;
+mod drip;
+mod echo;
 mod headers;
 mod healthz;
+mod http_version;
 mod index;
 mod ip;
+mod jwt;
 mod method;
+mod oauth;
+mod range;
 mod redirect;
+mod session;
+mod sse;
 mod status_code;
+mod tls;
 mod user_agent;
+mod websocket;
+
+/// Redirects also take requests with a body, to follow how clients re-send it
+const REDIRECT_METHODS: [Method; 3] = [Method::GET, Method::POST, Method::PUT];
+
+#[derive(Debug, Default)]
+pub struct Config {
+    /// PEM encoded CA certificate to publish when running self-signed TLS
+    pub ca_certificate: Option<String>,
+    /// Secret for HS256 tokens, generated when unset
+    pub jwt_secret: Option<String>,
+    /// Secret for session cookies, a random key when unset
+    pub session_key: Option<String>,
+}
+
+pub fn router(config: Config) -> Router {
+    let digest_auth = crate::service::auth::DigestAuth::default();
+    // Tokens from the OAuth server can be checked by /jwt/ES256 too
+    let es256 = Arc::new(SigningKey::ecdsa());
+    let oauth = OAuth::new(es256.clone());
+    let jwt_secret = config
+        .jwt_secret
+        .unwrap_or_else(|| format!("{:032x}", rand::random::<u128>()));
+    let jwt = Jwt::new(jwt_secret.as_bytes(), es256);
+    let session = match config.session_key {
+        Some(secret) => Session::new(secret.as_bytes()),
+        None => Session::generate(),
+    };
 
-pub fn router() -> Router {
     let builder = Router::builder()
         .install(
             crate::service::ip::ip,
@@ -26,6 +70,11 @@ pub fn router() -> Router {
             crate::service::user_agent::user_agent,
             route(path!("user-agent")).description("Returns user-agent"),
         )
+        .install(
+            crate::service::http_version::http_version,
+            route(path!("http-version"))
+                .description("Returns the negotiated HTTP protocol version"),
+        )
         .install(
             crate::service::headers::headers,
             route(path!("headers")).description("Returns headers"),
@@ -60,25 +109,148 @@ pub fn router() -> Router {
                 .method(Method::DELETE)
                 .description("Returns DELETE data"),
         )
+        .install(
+            crate::service::method::anything,
+            route(path!("anything" / [*rest])).any_method().description(
+                "Returns request data as JSON for any method and sub-path",
+            ),
+        )
+        .install(
+            crate::service::cors::cors,
+            route(path!("cors")).any_method().description(
+                "Returns the Access-Control-* headers given by allow_origin \
+                 (or reflect), allow_methods, allow_headers, \
+                 allow_credentials, allow_private_network, expose_headers \
+                 and max_age parameters, with an optional status",
+            ),
+        )
         .install(
             crate::service::status_code::status_code,
             route(path!("status" / code))
-                .description("Returns given HTTP Status code")
+                .description(
+                    "Returns given HTTP Status code (200 to 999), or a \
+                     random one of weighted codes (200:0.9,500:0.1), accepts \
+                     optional seed, retry_after and reason (HTTP/1.1 only) \
+                     parameters",
+                )
                 .add_example_param("code", "418"),
         )
         .install(
             crate::service::auth::basic,
             route(path!("basic-auth" / user / passwd))
-                .description("HTTP Basic Auth Challenge")
+                .description(
+                    "HTTP Basic Auth Challenge, with optional realm, \
+                     charset=UTF-8 and comma separated schemes parameters",
+                )
                 .add_example_param("user", "user")
                 .add_example_param("passwd", "passwd"),
         )
+        .install(
+            crate::service::auth::hidden_basic,
+            route(path!("hidden-basic-auth" / user / passwd))
+                .description("HTTP Basic Auth answering 404 when unauthorized")
+                .add_example_param("user", "user")
+                .add_example_param("passwd", "passwd"),
+        )
+        .install(
+            digest_auth.clone(),
+            route(path!("digest-auth" / qop / user / passwd))
+                .description("HTTP Digest Auth Challenge using MD5")
+                .add_example_param("qop", "auth")
+                .add_example_param("user", "user")
+                .add_example_param("passwd", "passwd"),
+        )
+        .install(
+            digest_auth,
+            route(path!("digest-auth" / qop / user / passwd / algorithm))
+                .description(
+                    "HTTP Digest Auth Challenge, qop is auth or auth-int and \
+                     algorithm is MD5, SHA-256 or their -sess variants",
+                )
+                .add_example_param("qop", "auth")
+                .add_example_param("user", "user")
+                .add_example_param("passwd", "passwd")
+                .add_example_param("algorithm", "SHA-256"),
+        )
         .install(
             crate::service::auth::bearer,
             route(path!("bearer-auth" / token))
                 .description("Bearer Auth Challenge")
                 .add_example_param("token", "random-token"),
         )
+        .install(
+            with_state(jwt.clone(), Jwt::jwks),
+            route(path!("jwt" / "jwks")).description(
+                "JSON Web Key Set of the RS256 and ES256 keys used by /jwt",
+            ),
+        )
+        .install(
+            with_state(jwt.clone(), Jwt::mint),
+            route(path!("jwt" / alg / "token"))
+                .description(
+                    "Mints a token signed with alg (HS256, RS256 or ES256), \
+                     accepts optional sub, iss, aud, expires_in and \
+                     not_before_in parameters",
+                )
+                .add_example_param("alg", "HS256"),
+        )
+        .install(
+            with_state(jwt, Jwt::validate),
+            route(path!("jwt" / alg))
+                .description(
+                    "Validates a Bearer token signed with alg and returns its \
+                     claims, accepts optional iss and aud parameters",
+                )
+                .add_example_param("alg", "HS256"),
+        )
+        .install(
+            with_state(oauth.clone(), OAuth::authorize),
+            route(path!("oauth" / "authorize")).description(
+                "OAuth 2.0 authorization endpoint that consents \
+                 immediately, supporting PKCE and a login_hint subject",
+            ),
+        )
+        .install(
+            with_state(oauth.clone(), OAuth::token),
+            route(path!("oauth" / "token"))
+                .method(Method::POST)
+                .description(
+                    "OAuth 2.0 token endpoint for the authorization_code, \
+                     client_credentials and refresh_token grants",
+                ),
+        )
+        .install(
+            with_state(oauth.clone(), OAuth::introspect),
+            route(path!("oauth" / "introspect"))
+                .method(Method::POST)
+                .description("OAuth 2.0 token introspection"),
+        )
+        .install(
+            with_state(oauth.clone(), OAuth::revoke),
+            route(path!("oauth" / "revoke"))
+                .method(Method::POST)
+                .description("OAuth 2.0 token revocation"),
+        )
+        .install(
+            with_state(oauth.clone(), OAuth::userinfo),
+            route(path!("oauth" / "userinfo"))
+                .description("OpenID Connect UserInfo for a Bearer token"),
+        )
+        .install(
+            with_state(oauth.clone(), OAuth::jwks),
+            route(path!("oauth" / "jwks"))
+                .description("JSON Web Key Set signing the issued tokens"),
+        )
+        .install(
+            with_state(oauth.clone(), OAuth::discovery),
+            route(path!(".well-known" / "openid-configuration"))
+                .description("OpenID Connect discovery document"),
+        )
+        .install(
+            with_state(oauth, OAuth::discovery),
+            route(path!(".well-known" / "oauth-authorization-server"))
+                .description("OAuth 2.0 authorization server metadata"),
+        )
         .install(
             crate::service::headers::response_headers,
             route(path!("response-headers"))
@@ -88,27 +260,67 @@ pub fn router() -> Router {
         .install(
             crate::service::redirect::redirect,
             route(path!("redirect" / n))
+                .methods(REDIRECT_METHODS)
                 .description("302 Redirects n times")
                 .add_example_param("n", "5"),
         )
+        .install(
+            crate::service::redirect::redirect,
+            route(path!("redirect" / n / status))
+                .methods(REDIRECT_METHODS)
+                .description(
+                    "Redirects n times with status 301, 302, 303, 307 or 308, \
+                     ending at /anything",
+                )
+                .add_example_param("n", "5")
+                .add_example_param("status", "307"),
+        )
         .install(
             crate::service::redirect::to,
             route(path!("redirect-to"))
-                .description("302 Redirects to the url= URL")
+                .methods(REDIRECT_METHODS)
+                .description(
+                    "Redirects to the url= URL, with the 3xx status_code= \
+                     status or 302",
+                )
                 .add_example_param("url", "http://example.com"),
         )
         .install(
             crate::service::redirect::absolute,
             route(path!("absolute-redirect" / n))
+                .methods(REDIRECT_METHODS)
                 .description("302 Absolute redirects n times")
                 .add_example_param("n", "5"),
         )
+        .install(
+            crate::service::redirect::absolute,
+            route(path!("absolute-redirect" / n / status))
+                .methods(REDIRECT_METHODS)
+                .description(
+                    "Absolute redirects n times with status 301, 302, 303, \
+                     307 or 308, ending at /anything",
+                )
+                .add_example_param("n", "5")
+                .add_example_param("status", "307"),
+        )
         .install(
             crate::service::redirect::relative,
             route(path!("relative-redirect" / n))
+                .methods(REDIRECT_METHODS)
                 .description("302 Relative redirects n times")
                 .add_example_param("n", "5"),
         )
+        .install(
+            crate::service::redirect::relative,
+            route(path!("relative-redirect" / n / status))
+                .methods(REDIRECT_METHODS)
+                .description(
+                    "Relative redirects n times with status 301, 302, 303, \
+                     307 or 308, ending at /anything",
+                )
+                .add_example_param("n", "5")
+                .add_example_param("status", "307"),
+        )
         .install(
             crate::service::cookies::cookies,
             route(path!("cookies")).description("Returns cookie data"),
@@ -116,20 +328,84 @@ pub fn router() -> Router {
         .install(
             crate::service::cookies::set_cookies,
             route(path!("cookies" / "set"))
-                .description("Sets one or more simple cookies")
+                .description(
+                    "Sets one or more cookies, with attributes such as \
+                     Path or Max-Age, then redirects to /cookies if asked to",
+                )
                 .add_example_param("key", "val"),
         )
+        .install(
+            crate::service::cookies::delete_cookies,
+            route(path!("cookies" / "delete"))
+                .description("Expires one or more cookies"),
+        )
+        .install(
+            with_state(session.clone(), Session::login),
+            route(path!("session" / "login")).description(
+                "Starts a session in a signed or encrypted cookie, accepts \
+                 optional user, mode (signed or encrypted), expires_in and \
+                 redirect parameters",
+            ),
+        )
+        .install(
+            with_state(session.clone(), Session::whoami),
+            route(path!("session" / "whoami")).description(
+                "Returns the session, or a 401 explaining why its cookie \
+                 was rejected",
+            ),
+        )
+        .install(
+            with_state(session.clone(), Session::rotate),
+            route(path!("session" / "rotate")).description(
+                "Issues a new session cookie for the same user, accepts an \
+                 optional redirect parameter",
+            ),
+        )
+        .install(
+            with_state(session, Session::logout),
+            route(path!("session" / "logout"))
+                .description("Expires the session cookies"),
+        )
         .install(
             crate::service::delay::delay,
             route(path!("delay" / n))
                 .description("Delays responding for min(n, 10) seconds")
                 .add_example_param("n", "3"),
         )
+        .install(
+            crate::service::drip::drip,
+            route(path!("drip"))
+                .description(
+                    "Drips numbytes bytes evenly over duration seconds after \
+                     an initial delay, accepts optional numbytes, duration, \
+                     delay and code parameters (capped at 10 KiB and 10s)",
+                )
+                .add_example_param("numbytes", "5"),
+        )
         .install(
             crate::service::cache::cache,
             route(path!("cache")).description(
-                "Returns 200 unless an If-Modified-Since or If-None-Match \
-                 header is provided, then it returns a 304",
+                "Returns 304 or 412 when If-None-Match, If-Modified-Since, \
+                 If-Match or If-Unmodified-Since call for it, accepts an \
+                 optional last_modified parameter",
+            ),
+        )
+        .install(
+            crate::service::cache::etag,
+            route(path!("etag" / etag))
+                .any_method()
+                .description(
+                    "Answers If-None-Match and If-Match as a resource with \
+                     the given ETag, weak with weak=true",
+                )
+                .add_example_param("etag", "abc"),
+        )
+        .install(
+            crate::service::cache::cache_control,
+            route(path!("cache-control")).description(
+                "Returns a changing body with the Cache-Control directives \
+                 given as parameters (no-store, max-age=60, ...), accepts \
+                 optional vary, expires, age and date_skew parameters",
             ),
         )
         .install(
@@ -138,15 +414,40 @@ pub fn router() -> Router {
                 .description("Sets a Cache-Control header for n seconds")
                 .add_example_param("n", "10"),
         )
+        .install(
+            crate::service::compression::gzip,
+            route(path!("gzip")).description("Returns gzip-encoded data"),
+        )
+        .install(
+            crate::service::compression::deflate,
+            route(path!("deflate")).description("Returns deflate-encoded data"),
+        )
+        .install(
+            crate::service::compression::brotli,
+            route(path!("brotli")).description("Returns brotli-encoded data"),
+        )
+        .install(
+            crate::service::compression::zstd,
+            route(path!("zstd")).description("Returns zstd-encoded data"),
+        )
         .install(
             crate::service::bytes::bytes,
             route(path!("bytes" / n))
                 .description(
                     "Generates n random bytes of binary data, accepts \
-                        optional seed integer parameter",
+                        optional seed integer parameter and byte ranges",
                 )
                 .add_example_param("n", "256"),
         )
+        .install(
+            crate::service::range::range,
+            route(path!("range" / n))
+                .description(
+                    "Returns n (at most 100 KiB) bytes of the alphabet, \
+                     accepts byte ranges and If-Range",
+                )
+                .add_example_param("n", "1024"),
+        )
         .install(
             crate::service::bytes::stream_bytes,
             route(path!("stream-bytes" / n))
@@ -155,8 +456,45 @@ pub fn router() -> Router {
                         optional seed and chunk_size integer parameters",
                 )
                 .add_example_param("n", "256"),
+        )
+        .install(
+            crate::service::sse::sse,
+            route(path!("sse"))
+                .description(
+                    "Streams server-sent events, accepts optional count, \
+                     interval (ms), event, id, retry and close_after \
+                     parameters and resumes from Last-Event-ID",
+                )
+                .add_example_param("count", "5"),
+        )
+        .install(
+            crate::service::websocket::echo,
+            route(path!("ws" / "echo")).description(
+                "WebSocket that echoes text and binary messages, accepts \
+                 optional ping, limit, close_code, close_reason, drop_after \
+                 and fragment parameters",
+            ),
+        )
+        .install(
+            crate::service::websocket::stream,
+            route(path!("ws" / "stream" / n))
+                .description(
+                    "WebSocket that sends n (at most 100) random binary \
+                     messages, accepts optional seed and size integer \
+                     parameters",
+                )
+                .add_example_param("n", "10"),
         );
 
+    let builder = match config.ca_certificate {
+        Some(pem) => builder.install(
+            crate::service::tls::CaCertificate::from(pem),
+            route(path!("tls" / "ca.pem"))
+                .description("Returns the self-signed TLS CA certificate"),
+        ),
+        None => builder,
+    };
+
     let index_route: Route = route(path!()).description("This page").into();
 
     let routes = std::iter::once(&index_route).chain(builder.routes());
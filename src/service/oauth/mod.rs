@@ -0,0 +1,1037 @@
+use super::jwt::key::SigningKey;
+use super::jwt::{bearer_challenge, describe, issuer};
+use crate::headers::authorization::{Basic, Bearer};
+use crate::headers::{
+    Authorization, CacheControl, Challenge, HeaderMapExt, Pragma,
+    WWWAuthenticate,
+};
+use crate::http::{
+    Error, Request, Result, StatusCode, bad_request, json, json_error,
+    redirect_to, response,
+};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use http_body_util::BodyExt;
+use jsonwebtoken::errors;
+use jsonwebtoken::get_current_timestamp;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+const CODE_TTL: Duration = Duration::from_secs(60);
+const REFRESH_TOKEN_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const ACCESS_TOKEN_TTL: u64 = 60 * 60;
+const MAX_ENTRIES: usize = 4096;
+
+/// Subject of user grants when the client doesn't send a `login_hint`
+const DEFAULT_SUBJECT: &str = "user";
+
+const SCOPES: &[&str] = &["openid", "profile", "email"];
+
+fn random_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+fn has_scope(scope: &str, name: &str) -> bool {
+    scope.split_whitespace().any(|scope| scope == name)
+}
+
+/// Single use values such as codes and refresh tokens
+struct Store<T>(HashMap<String, (Instant, T)>);
+
+impl<T> Default for Store<T> {
+    fn default() -> Self {
+        Store(HashMap::new())
+    }
+}
+
+impl<T> Store<T> {
+    fn insert(&mut self, ttl: Duration, value: T) -> String {
+        let now = Instant::now();
+        self.0.retain(|_, (expires, _)| *expires > now);
+        if self.0.len() >= MAX_ENTRIES {
+            let oldest = self
+                .0
+                .iter()
+                .min_by_key(|(_, (expires, _))| *expires)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.0.remove(&oldest);
+            }
+        }
+
+        let key = random_token();
+        self.0.insert(key.clone(), (now + ttl, value));
+        key
+    }
+
+    fn get(&self, key: &str) -> Option<&T> {
+        self.0
+            .get(key)
+            .filter(|(expires, _)| *expires > Instant::now())
+            .map(|(_, value)| value)
+    }
+
+    fn take(&mut self, key: &str) -> Option<T> {
+        self.take_entry(key).map(|(_, value)| value)
+    }
+
+    /// Take a value with its expiry, so it can be restored if it turns out
+    /// not to be usable
+    fn take_entry(&mut self, key: &str) -> Option<(Instant, T)> {
+        self.0
+            .remove(key)
+            .filter(|(expires, _)| *expires > Instant::now())
+    }
+
+    fn restore(&mut self, key: &str, entry: (Instant, T)) {
+        self.0.insert(key.to_owned(), entry);
+    }
+}
+
+/// PKCE code challenge methods (RFC 7636)
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pkce {
+    Plain,
+    S256,
+}
+
+impl Pkce {
+    fn verify(self, verifier: &str, challenge: &str) -> bool {
+        is_pkce_value(verifier)
+            && match self {
+                Self::Plain => verifier == challenge,
+                Self::S256 => {
+                    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier))
+                        == challenge
+                }
+            }
+    }
+}
+
+impl FromStr for Pkce {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "plain" => Ok(Self::Plain),
+            "S256" => Ok(Self::S256),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Verifiers and challenges are 43 to 128 unreserved characters
+fn is_pkce_value(value: &str) -> bool {
+    (43..=128).contains(&value.len())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c))
+}
+
+/// What a client was authorized to do, and on whose behalf
+#[derive(Clone, Debug)]
+struct Grant {
+    client_id: String,
+    subject: String,
+    scope: String,
+}
+
+struct AuthorizationCode {
+    grant: Grant,
+    redirect_uri: String,
+    nonce: Option<String>,
+    challenge: Option<(Pkce, String)>,
+}
+
+/// Tokens to hand out for a successful token request
+struct Issue {
+    grant: Grant,
+    /// A narrower scope for the access token than the grant's
+    scope: Option<String>,
+    nonce: Option<String>,
+    /// Whether the grant acts for a user, which adds refresh and ID tokens
+    user: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AccessClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    client_id: String,
+    scope: String,
+    iat: u64,
+    exp: u64,
+    jti: String,
+}
+
+/// An RFC 6749 error response
+fn oauth_error(status: StatusCode, error: &str, description: &str) -> Error {
+    let body = json!({
+        "error": error,
+        "error_description": description,
+    });
+    json_error(status, &body)
+}
+
+fn invalid_request(description: &str) -> Error {
+    oauth_error(StatusCode::BAD_REQUEST, "invalid_request", description)
+}
+
+fn invalid_grant(description: &str) -> Error {
+    oauth_error(StatusCode::BAD_REQUEST, "invalid_grant", description)
+}
+
+fn invalid_client(description: &str) -> Error {
+    let mut err =
+        oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", description);
+    if let Error::Failure(res) = &mut err {
+        res.headers_mut()
+            .typed_insert(WWWAuthenticate::from(Challenge::basic("oauth")));
+    }
+    err
+}
+
+/// Responses carrying tokens must not be cached (RFC 6749 5.1)
+#[allow(clippy::result_large_err)]
+fn token_response(body: &Value) -> Result {
+    let mut res = json(body)?;
+    res.headers_mut()
+        .typed_insert(CacheControl::new().with_no_store());
+    res.headers_mut().typed_insert(Pragma::no_cache());
+    Ok(res)
+}
+
+async fn read_form<T: DeserializeOwned>(
+    req: &mut Request,
+) -> std::result::Result<T, Error> {
+    let body = req
+        .body_mut()
+        .collect()
+        .await
+        .map_err(|_| bad_request())?
+        .to_bytes();
+    serde_urlencoded::from_bytes(&body)
+        .map_err(|err| invalid_request(&err.to_string()))
+}
+
+/// The client named by `Authorization: Basic` or the `client_id` field.
+///
+/// Secrets aren't checked so any client can use the server.
+#[allow(clippy::result_large_err)]
+fn client_id(
+    req: &Request,
+    client_id: Option<&str>,
+) -> std::result::Result<Option<String>, Error> {
+    let basic = req
+        .typed_header::<Authorization<Basic>>()
+        .map(|auth| auth.username().to_owned());
+    match (basic, client_id) {
+        (Some(basic), Some(client_id)) if basic != client_id => Err(
+            invalid_client("client_id doesn't match the Authorization header"),
+        ),
+        (basic, client_id) => Ok(basic.or_else(|| client_id.map(Into::into))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AuthorizeQuery {
+    response_type: Option<String>,
+    client_id: Option<String>,
+    redirect_uri: Option<String>,
+    scope: Option<String>,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+    login_hint: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TokenForm {
+    grant_type: Option<String>,
+    code: Option<String>,
+    redirect_uri: Option<String>,
+    code_verifier: Option<String>,
+    client_id: Option<String>,
+    refresh_token: Option<String>,
+    scope: Option<String>,
+}
+
+/// Body of introspection and revocation requests, `token_type_hint` is
+/// ignored since tokens are told apart by their shape
+#[derive(Deserialize)]
+pub struct TokenHintForm {
+    token: Option<String>,
+}
+
+struct OAuthState {
+    key: Arc<SigningKey>,
+    codes: Mutex<Store<AuthorizationCode>>,
+    refresh_tokens: Mutex<Store<Grant>>,
+    /// Revoked access token ids, until the tokens expire
+    revoked: Mutex<HashMap<String, u64>>,
+}
+
+/// A mock OAuth 2.0 and OpenID Connect authorization server that consents
+/// to every request
+#[derive(Clone)]
+pub struct OAuth(Arc<OAuthState>);
+
+impl OAuth {
+    /// A server signing tokens with `key`, which must be asymmetric to be
+    /// published in the JWKS
+    pub fn new(key: Arc<SigningKey>) -> Self {
+        OAuth(Arc::new(OAuthState {
+            key,
+            codes: Default::default(),
+            refresh_tokens: Default::default(),
+            revoked: Default::default(),
+        }))
+    }
+
+    fn decode(&self, token: &str) -> errors::Result<AccessClaims> {
+        let mut validation = self.0.key.validation();
+        validation.set_required_spec_claims(&["exp", "sub"]);
+        self.0.key.verify(token, &validation)
+    }
+
+    fn verify_access_token(
+        &self,
+        token: &str,
+    ) -> std::result::Result<AccessClaims, String> {
+        let claims = self.decode(token).map_err(|err| describe(&err))?;
+        match self.0.revoked.lock().unwrap().contains_key(&claims.jti) {
+            true => Err(String::from("the token has been revoked")),
+            false => Ok(claims),
+        }
+    }
+
+    fn issue_code(
+        &self,
+        query: &AuthorizeQuery,
+        client_id: String,
+        redirect_uri: String,
+    ) -> std::result::Result<String, (&'static str, &'static str)> {
+        if query.response_type.as_deref() != Some("code") {
+            return Err((
+                "unsupported_response_type",
+                "only the code response type is supported",
+            ));
+        }
+
+        let challenge = match &query.code_challenge {
+            Some(challenge) => {
+                let method = query
+                    .code_challenge_method
+                    .as_deref()
+                    .unwrap_or("plain")
+                    .parse::<Pkce>()
+                    .map_err(|_| {
+                        ("invalid_request", "transform algorithm not supported")
+                    })?;
+                if !is_pkce_value(challenge) {
+                    return Err(("invalid_request", "invalid code_challenge"));
+                }
+                Some((method, challenge.clone()))
+            }
+            None if query.code_challenge_method.is_some() => {
+                return Err(("invalid_request", "missing code_challenge"));
+            }
+            None => None,
+        };
+
+        let code = AuthorizationCode {
+            grant: Grant {
+                client_id,
+                subject: query
+                    .login_hint
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_SUBJECT.to_owned()),
+                scope: query.scope.clone().unwrap_or_default(),
+            },
+            redirect_uri,
+            nonce: query.nonce.clone(),
+            challenge,
+        };
+        Ok(self.0.codes.lock().unwrap().insert(CODE_TTL, code))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn exchange_code(
+        &self,
+        form: TokenForm,
+        client_id: Option<String>,
+    ) -> std::result::Result<Issue, Error> {
+        let code = form
+            .code
+            .as_deref()
+            .ok_or_else(|| invalid_request("missing code"))?;
+        let code =
+            self.0.codes.lock().unwrap().take(code).ok_or_else(|| {
+                invalid_grant("unknown, expired or used code")
+            })?;
+
+        if client_id.as_ref() != Some(&code.grant.client_id) {
+            return Err(invalid_grant("the code was issued to another client"));
+        }
+        if form.redirect_uri.as_ref() != Some(&code.redirect_uri) {
+            return Err(invalid_grant(
+                "redirect_uri doesn't match the authorization request",
+            ));
+        }
+        if let Some((method, challenge)) = &code.challenge {
+            let verifier = form.code_verifier.as_deref().unwrap_or_default();
+            if !method.verify(verifier, challenge) {
+                return Err(invalid_grant("PKCE verification failed"));
+            }
+        }
+
+        Ok(Issue {
+            grant: code.grant,
+            scope: None,
+            nonce: code.nonce,
+            user: true,
+        })
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn refresh(
+        &self,
+        form: TokenForm,
+        client_id: Option<String>,
+    ) -> std::result::Result<Issue, Error> {
+        let token = form
+            .refresh_token
+            .as_deref()
+            .ok_or_else(|| invalid_request("missing refresh_token"))?;
+
+        // Refresh tokens are rotated on every use, so take the grant up
+        // front and only put it back when the request is rejected
+        let mut refresh_tokens = self.0.refresh_tokens.lock().unwrap();
+        let (expires, grant) =
+            refresh_tokens.take_entry(token).ok_or_else(|| {
+                invalid_grant("unknown, expired or revoked refresh token")
+            })?;
+        let rejection = if client_id
+            .is_some_and(|client_id| client_id != grant.client_id)
+        {
+            Some(invalid_grant(
+                "the refresh token was issued to another client",
+            ))
+        } else if let Some(scope) = &form.scope
+            && !scope
+                .split_whitespace()
+                .all(|name| has_scope(&grant.scope, name))
+        {
+            Some(oauth_error(
+                StatusCode::BAD_REQUEST,
+                "invalid_scope",
+                "the scope exceeds the original grant",
+            ))
+        } else {
+            None
+        };
+        if let Some(err) = rejection {
+            refresh_tokens.restore(token, (expires, grant));
+            return Err(err);
+        }
+
+        Ok(Issue {
+            grant,
+            scope: form.scope,
+            nonce: None,
+            user: true,
+        })
+    }
+
+    fn issue_tokens(&self, issuer: &str, issue: Issue) -> Value {
+        let Issue {
+            grant,
+            scope,
+            nonce,
+            user,
+        } = issue;
+        let scope = scope.unwrap_or_else(|| grant.scope.clone());
+        let now = get_current_timestamp();
+
+        let claims = AccessClaims {
+            iss: issuer.to_owned(),
+            sub: grant.subject.clone(),
+            aud: grant.client_id.clone(),
+            client_id: grant.client_id.clone(),
+            scope: scope.clone(),
+            iat: now,
+            exp: now + ACCESS_TOKEN_TTL,
+            jti: random_token(),
+        };
+        let mut body = json!({
+            "access_token": self.0.key.sign(&claims),
+            "token_type": "Bearer",
+            "expires_in": ACCESS_TOKEN_TTL,
+            "scope": scope,
+        });
+
+        if !user {
+            return body;
+        }
+        if has_scope(&scope, "openid") {
+            let mut id_claims = json!({
+                "iss": issuer,
+                "sub": grant.subject,
+                "aud": grant.client_id,
+                "iat": now,
+                "exp": now + ACCESS_TOKEN_TTL,
+            });
+            if let Some(nonce) = nonce {
+                id_claims["nonce"] = nonce.into();
+            }
+            body["id_token"] = self.0.key.sign(&id_claims).into();
+        }
+        body["refresh_token"] = self
+            .0
+            .refresh_tokens
+            .lock()
+            .unwrap()
+            .insert(REFRESH_TOKEN_TTL, grant)
+            .into();
+        body
+    }
+
+    pub async fn authorize(self, req: Request) -> Result {
+        let query = req
+            .query::<AuthorizeQuery>()
+            .map_err(|err| invalid_request(&err.to_string()))?;
+        let client_id = query
+            .client_id
+            .clone()
+            .ok_or_else(|| invalid_request("missing client_id"))?;
+        // Without a valid redirect_uri errors can't be sent to the client
+        let redirect_uri = query
+            .redirect_uri
+            .clone()
+            .filter(|uri| Url::parse(uri).is_ok())
+            .ok_or_else(|| {
+                invalid_request("missing or invalid redirect_uri")
+            })?;
+
+        let mut redirect = Url::parse(&redirect_uri).unwrap();
+        let code = self.issue_code(&query, client_id, redirect_uri);
+        {
+            let mut pairs = redirect.query_pairs_mut();
+            match &code {
+                Ok(code) => pairs.append_pair("code", code),
+                Err((error, description)) => pairs
+                    .append_pair("error", error)
+                    .append_pair("error_description", description),
+            };
+            if let Some(state) = &query.state {
+                pairs.append_pair("state", state);
+            }
+        }
+        redirect_to(redirect.as_str().parse().map_err(|_| bad_request())?)
+    }
+
+    pub async fn token(self, mut req: Request) -> Result {
+        let form = read_form::<TokenForm>(&mut req).await?;
+        let client_id = client_id(&req, form.client_id.as_deref())?;
+        let issuer = issuer(&req)?;
+
+        let issue = match form.grant_type.as_deref() {
+            Some("authorization_code") => {
+                self.exchange_code(form, client_id)?
+            }
+            Some("refresh_token") => self.refresh(form, client_id)?,
+            Some("client_credentials") => {
+                let client_id = client_id.ok_or_else(|| {
+                    invalid_client("client authentication required")
+                })?;
+                Issue {
+                    grant: Grant {
+                        subject: client_id.clone(),
+                        client_id,
+                        scope: form.scope.unwrap_or_default(),
+                    },
+                    scope: None,
+                    nonce: None,
+                    user: false,
+                }
+            }
+            Some(_) => {
+                return Err(oauth_error(
+                    StatusCode::BAD_REQUEST,
+                    "unsupported_grant_type",
+                    "supported grants are authorization_code, \
+                     client_credentials and refresh_token",
+                ));
+            }
+            None => return Err(invalid_request("missing grant_type")),
+        };
+
+        token_response(&self.issue_tokens(&issuer, issue))
+    }
+
+    /// Token introspection (RFC 7662)
+    pub async fn introspect(self, mut req: Request) -> Result {
+        let form = read_form::<TokenHintForm>(&mut req).await?;
+        let token =
+            form.token.ok_or_else(|| invalid_request("missing token"))?;
+
+        let body = match self.verify_access_token(&token) {
+            Ok(claims) => {
+                let mut body = json!(claims);
+                body["active"] = true.into();
+                body["token_type"] = "Bearer".into();
+                body
+            }
+            Err(_) => match self.0.refresh_tokens.lock().unwrap().get(&token) {
+                Some(grant) => json!({
+                    "active": true,
+                    "token_type": "refresh_token",
+                    "client_id": grant.client_id,
+                    "sub": grant.subject,
+                    "scope": grant.scope,
+                }),
+                None => json!({ "active": false }),
+            },
+        };
+        token_response(&body)
+    }
+
+    /// Token revocation (RFC 7009), unknown tokens are ignored
+    pub async fn revoke(self, mut req: Request) -> Result {
+        let form = read_form::<TokenHintForm>(&mut req).await?;
+        let token =
+            form.token.ok_or_else(|| invalid_request("missing token"))?;
+
+        match self.decode(&token) {
+            Ok(claims) => {
+                let now = get_current_timestamp();
+                let mut revoked = self.0.revoked.lock().unwrap();
+                revoked.retain(|_, exp| *exp > now);
+                revoked.insert(claims.jti, claims.exp);
+            }
+            Err(_) => {
+                self.0.refresh_tokens.lock().unwrap().take(&token);
+            }
+        }
+        response().into()
+    }
+
+    pub async fn userinfo(self, req: Request) -> Result {
+        let bearer = req
+            .typed_header::<Authorization<Bearer>>()
+            .ok_or_else(|| bearer_challenge(None))?;
+        let claims = self
+            .verify_access_token(bearer.token())
+            .map_err(|description| bearer_challenge(Some(&description)))?;
+
+        let mut body = json!({ "sub": claims.sub });
+        if has_scope(&claims.scope, "profile") {
+            body["name"] = claims.sub.clone().into();
+            body["preferred_username"] = claims.sub.clone().into();
+        }
+        if has_scope(&claims.scope, "email") {
+            body["email"] = format!("{}@example.com", claims.sub).into();
+            body["email_verified"] = true.into();
+        }
+        json(&body)
+    }
+
+    /// Authorization server metadata, for both OpenID Connect discovery and
+    /// RFC 8414
+    pub async fn discovery(self, req: Request) -> Result {
+        let issuer = issuer(&req)?;
+        json(&json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{issuer}/oauth/authorize"),
+            "token_endpoint": format!("{issuer}/oauth/token"),
+            "introspection_endpoint": format!("{issuer}/oauth/introspect"),
+            "revocation_endpoint": format!("{issuer}/oauth/revoke"),
+            "userinfo_endpoint": format!("{issuer}/oauth/userinfo"),
+            "jwks_uri": format!("{issuer}/oauth/jwks"),
+            "scopes_supported": SCOPES,
+            "response_types_supported": ["code"],
+            "grant_types_supported": [
+                "authorization_code",
+                "client_credentials",
+                "refresh_token",
+            ],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["ES256"],
+            "code_challenge_methods_supported": ["S256", "plain"],
+            "token_endpoint_auth_methods_supported": [
+                "client_secret_basic",
+                "client_secret_post",
+                "none",
+            ],
+        }))
+    }
+
+    pub async fn jwks(self, _: Request) -> Result {
+        json(&json!({ "keys": [self.0.key.jwk()] }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::handler::with_state;
+    use crate::headers::ContentType;
+    use crate::test::*;
+    use hyper::Method;
+    use hyper::header::{LOCATION, WWW_AUTHENTICATE};
+    use std::future::Future;
+
+    const REDIRECT_URI: &str = "http://client.example/callback";
+    const VERIFIER: &str = "dBjftJeZ4CVP-mJ92K9jDWgB6ZSE1pSNm7Pu-L4tHL0";
+    const CHALLENGE: &str = "CdrhWvKKBi2WTyDFC1OtdJqULubO0cKwlZNNPd8mmhQ";
+
+    fn oauth() -> OAuth {
+        OAuth::new(Arc::new(SigningKey::ecdsa()))
+    }
+
+    fn redirect_params(res: &hyper::Response<crate::http::Body>) -> Value {
+        let location = res.headers()[LOCATION].to_str().unwrap();
+        let url = Url::parse(location).unwrap();
+        url.query_pairs()
+            .map(|(key, value)| (key.into_owned(), Value::from(value)))
+            .collect()
+    }
+
+    async fn authorize(oauth: &OAuth, query: &str) -> Value {
+        let res = request()
+            .path(&format!(
+                "/oauth/authorize?client_id=app&redirect_uri={REDIRECT_URI}\
+                 &state=xyz&{query}"
+            ))
+            .handle(with_state(oauth.clone(), OAuth::authorize))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::FOUND);
+        redirect_params(&res)
+    }
+
+    async fn form<F, Fut>(oauth: &OAuth, endpoint: F, body: &str) -> Value
+    where
+        F: Fn(OAuth, Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result> + Send + 'static,
+    {
+        let res = request()
+            .method(Method::POST)
+            .header("host", "example.com")
+            .typed_header(ContentType::form_url_encoded())
+            .body(body)
+            .handle(with_state(oauth.clone(), endpoint))
+            .await
+            .unwrap();
+        res.read_body_json().await.unwrap()
+    }
+
+    async fn userinfo(
+        oauth: &OAuth,
+        token: &str,
+    ) -> hyper::Response<crate::http::Body> {
+        request()
+            .typed_header(Authorization::bearer(token).unwrap())
+            .handle(with_state(oauth.clone(), OAuth::userinfo))
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_pkce_verify() {
+        assert!(Pkce::S256.verify(VERIFIER, CHALLENGE));
+        assert!(Pkce::Plain.verify(VERIFIER, VERIFIER));
+        assert!(!Pkce::S256.verify(VERIFIER, VERIFIER));
+        assert!(!Pkce::Plain.verify("short", "short"));
+    }
+
+    #[tokio::test]
+    async fn test_discovery() {
+        let oauth = oauth();
+        let res = request()
+            .header("host", "example.com")
+            .handle(with_state(oauth.clone(), OAuth::discovery))
+            .await
+            .unwrap();
+
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["issuer"], "http://example.com");
+        assert_eq!(body["jwks_uri"], "http://example.com/oauth/jwks");
+    }
+
+    #[tokio::test]
+    async fn test_jwks() {
+        let oauth = oauth();
+        let res = request()
+            .handle(with_state(oauth.clone(), OAuth::jwks))
+            .await
+            .unwrap();
+
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["keys"][0]["kty"], "EC");
+        assert_eq!(body["keys"][0]["alg"], "ES256");
+    }
+
+    #[tokio::test]
+    async fn test_authorize_invalid_redirect_uri() {
+        let oauth = oauth();
+        let res = request()
+            .path("/oauth/authorize?response_type=code&client_id=app")
+            .handle(with_state(oauth.clone(), OAuth::authorize))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["error"], "invalid_request");
+    }
+
+    #[tokio::test]
+    async fn test_authorize_errors_redirect() {
+        let oauth = oauth();
+
+        let params = authorize(&oauth, "response_type=token").await;
+        assert_eq!(params["error"], "unsupported_response_type");
+        assert_eq!(params["state"], "xyz");
+
+        let params = authorize(
+            &oauth,
+            &format!(
+                "response_type=code&code_challenge={CHALLENGE}\
+                 &code_challenge_method=S512"
+            ),
+        )
+        .await;
+        assert_eq!(params["error"], "invalid_request");
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_flow() {
+        let oauth = oauth();
+        let params = authorize(
+            &oauth,
+            &format!(
+                "response_type=code&scope=openid+email&nonce=n-0S6\
+                 &login_hint=alice\
+                 &code_challenge={CHALLENGE}&code_challenge_method=S256"
+            ),
+        )
+        .await;
+        assert_eq!(params["state"], "xyz");
+        let code = params["code"].as_str().unwrap();
+
+        let exchange = format!(
+            "grant_type=authorization_code&code={code}&client_id=app\
+             &redirect_uri={REDIRECT_URI}&code_verifier={VERIFIER}"
+        );
+        let tokens = form(&oauth, OAuth::token, &exchange).await;
+        assert_eq!(tokens["token_type"], "Bearer");
+        assert_eq!(tokens["scope"], "openid email");
+        assert!(tokens["refresh_token"].is_string());
+
+        let id_token = tokens["id_token"].as_str().unwrap();
+        let key = &oauth.0.key;
+        let id_claims =
+            key.verify::<Value>(id_token, &key.validation()).unwrap();
+        assert_eq!(id_claims["iss"], "http://example.com");
+        assert_eq!(id_claims["sub"], "alice");
+        assert_eq!(id_claims["aud"], "app");
+        assert_eq!(id_claims["nonce"], "n-0S6");
+
+        let access_token = tokens["access_token"].as_str().unwrap();
+        let res = userinfo(&oauth, access_token).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["sub"], "alice");
+        assert_eq!(body["email"], "alice@example.com");
+
+        // Codes are single use
+        let tokens = form(&oauth, OAuth::token, &exchange).await;
+        assert_eq!(tokens["error"], "invalid_grant");
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_pkce_mismatch() {
+        let oauth = oauth();
+        let params = authorize(
+            &oauth,
+            &format!(
+                "response_type=code&code_challenge={CHALLENGE}\
+                 &code_challenge_method=S256"
+            ),
+        )
+        .await;
+        let code = params["code"].as_str().unwrap();
+
+        let tokens = form(
+            &oauth,
+            OAuth::token,
+            &format!(
+                "grant_type=authorization_code&code={code}&client_id=app\
+                 &redirect_uri={REDIRECT_URI}&code_verifier={CHALLENGE}"
+            ),
+        )
+        .await;
+        assert_eq!(tokens["error"], "invalid_grant");
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials() {
+        let oauth = oauth();
+        let res = request()
+            .method(Method::POST)
+            .header("host", "example.com")
+            .typed_header(Authorization::basic("service", "secret"))
+            .typed_header(ContentType::form_url_encoded())
+            .body("grant_type=client_credentials&scope=read")
+            .handle(with_state(oauth.clone(), OAuth::token))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["cache-control"], "no-store");
+        let tokens = res.read_body_json().await.unwrap();
+        assert_eq!(tokens["scope"], "read");
+        assert!(tokens.get("refresh_token").is_none());
+
+        let tokens =
+            form(&oauth, OAuth::token, "grant_type=client_credentials").await;
+        assert_eq!(tokens["error"], "invalid_client");
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_rotation() {
+        let oauth = oauth();
+        let params =
+            authorize(&oauth, "response_type=code&scope=openid+profile").await;
+        let code = params["code"].as_str().unwrap();
+        let tokens = form(
+            &oauth,
+            OAuth::token,
+            &format!(
+                "grant_type=authorization_code&code={code}&client_id=app\
+                 &redirect_uri={REDIRECT_URI}"
+            ),
+        )
+        .await;
+        let refresh_token = tokens["refresh_token"].as_str().unwrap();
+
+        let refresh = format!(
+            "grant_type=refresh_token&refresh_token={refresh_token}\
+             &scope=profile"
+        );
+        let refreshed = form(&oauth, OAuth::token, &refresh).await;
+        assert_eq!(refreshed["scope"], "profile");
+        assert!(refreshed.get("id_token").is_none());
+        assert_ne!(refreshed["refresh_token"], tokens["refresh_token"]);
+
+        let reused = form(&oauth, OAuth::token, &refresh).await;
+        assert_eq!(reused["error"], "invalid_grant");
+
+        let widened = form(
+            &oauth,
+            OAuth::token,
+            &format!(
+                "grant_type=refresh_token&refresh_token={}&scope=email",
+                refreshed["refresh_token"].as_str().unwrap()
+            ),
+        )
+        .await;
+        assert_eq!(widened["error"], "invalid_scope");
+
+        // A rejected refresh leaves the token usable
+        let retried = form(
+            &oauth,
+            OAuth::token,
+            &format!(
+                "grant_type=refresh_token&refresh_token={}",
+                refreshed["refresh_token"].as_str().unwrap()
+            ),
+        )
+        .await;
+        assert!(retried["access_token"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_expired() {
+        let oauth = oauth();
+        let grant = Grant {
+            client_id: String::from("app"),
+            subject: String::from(DEFAULT_SUBJECT),
+            scope: String::from("profile"),
+        };
+        let token = oauth
+            .0
+            .refresh_tokens
+            .lock()
+            .unwrap()
+            .insert(Duration::ZERO, grant);
+
+        let refresh = format!("grant_type=refresh_token&refresh_token={token}");
+        let expired = form(&oauth, OAuth::token, &refresh).await;
+        assert_eq!(expired["error"], "invalid_grant");
+
+        // The store is still usable afterwards
+        let expired = form(&oauth, OAuth::token, &refresh).await;
+        assert_eq!(expired["error"], "invalid_grant");
+    }
+
+    #[tokio::test]
+    async fn test_introspect_and_revoke() {
+        let oauth = oauth();
+        let tokens = form(
+            &oauth,
+            OAuth::token,
+            "grant_type=client_credentials&client_id=service",
+        )
+        .await;
+        let access_token = tokens["access_token"].as_str().unwrap();
+
+        let body = format!("token={access_token}");
+        let info = form(&oauth, OAuth::introspect, &body).await;
+        assert_eq!(info["active"], true);
+        assert_eq!(info["client_id"], "service");
+
+        let res = request()
+            .method(Method::POST)
+            .typed_header(ContentType::form_url_encoded())
+            .body(&body)
+            .handle(with_state(oauth.clone(), OAuth::revoke))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let info = form(&oauth, OAuth::introspect, &body).await;
+        assert_eq!(info, json!({ "active": false }));
+
+        let res = userinfo(&oauth, access_token).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers()[WWW_AUTHENTICATE],
+            "Bearer error=\"invalid_token\", \
+             error_description=\"the token has been revoked\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_userinfo_without_token() {
+        let oauth = oauth();
+        let res = request()
+            .handle(with_state(oauth.clone(), OAuth::userinfo))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(res.headers()[WWW_AUTHENTICATE], "Bearer");
+    }
+}
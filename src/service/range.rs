@@ -0,0 +1,383 @@
+use crate::headers::{
+    AcceptRanges, ContentLength, ContentRange, ContentType, ETag, IfRange,
+};
+use crate::http::{
+    Bytes, Request, Result, StatusCode, bad_request, body_from_stream, response,
+};
+use futures::prelude::*;
+use hyper::header::RANGE;
+use std::iter;
+
+const CHUNK_SIZE: usize = 4096;
+
+/// Largest `/range/{n}`, the same limit as httpbin
+const MAX_LEN: u32 = 100 * 1024;
+
+/// Range headers with more ranges than this are ignored
+const MAX_RANGES: usize = 64;
+
+/// Content that can regenerate any slice of itself on demand
+pub trait Representation: Clone + Send + Sync + 'static {
+    fn len(&self) -> u64;
+
+    /// Bytes `start..=end` of the content
+    fn slice(
+        self,
+        start: u64,
+        end: u64,
+    ) -> impl Iterator<Item = u8> + Send + Sync + 'static;
+}
+
+/// Lowercase alphabet repeated up to `len` bytes
+#[derive(Clone)]
+struct Alphabet(u64);
+
+impl Representation for Alphabet {
+    fn len(&self) -> u64 {
+        self.0
+    }
+
+    fn slice(
+        self,
+        start: u64,
+        end: u64,
+    ) -> impl Iterator<Item = u8> + Send + Sync + 'static {
+        (start..=end).map(|i| b'a' + (i % 26) as u8)
+    }
+}
+
+/// Resolve a `Range` header into inclusive byte offsets.
+///
+/// Returns `None` when the header is malformed or asks for too many ranges
+/// and must be ignored, and an empty list when none of the ranges can be
+/// satisfied. Overlapping and adjacent ranges are merged, in order of
+/// offset (RFC 9110 14.2).
+fn parse_ranges(header: &str, len: u64) -> Option<Vec<(u64, u64)>> {
+    let specs = header.trim().strip_prefix("bytes=")?;
+    if specs.split(',').count() > MAX_RANGES {
+        return None;
+    }
+
+    let mut ranges = vec![];
+    for spec in specs.split(',') {
+        let (start, end) = spec.trim().split_once('-')?;
+        let range = match (start, end) {
+            ("", "") => return None,
+            // Suffix range, the last `end` bytes
+            ("", end) => {
+                let suffix = end.parse::<u64>().ok()?;
+                (suffix > 0 && len > 0)
+                    .then(|| (len.saturating_sub(suffix), len - 1))
+            }
+            (start, "") => {
+                let start = start.parse::<u64>().ok()?;
+                (start < len).then(|| (start, len - 1))
+            }
+            (start, end) => {
+                let start = start.parse::<u64>().ok()?;
+                let end = end.parse::<u64>().ok()?;
+                if start > end {
+                    return None;
+                }
+                (start < len).then(|| (start, end.min(len - 1)))
+            }
+        };
+        ranges.extend(range);
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => {
+                last.1 = last.1.max(end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+    Some(merged)
+}
+
+fn data<R: Representation>(
+    content: R,
+    start: u64,
+    end: u64,
+) -> impl Stream<Item = Bytes> + Send + Sync + Unpin + 'static {
+    stream::iter(content.slice(start, end))
+        .chunks(CHUNK_SIZE)
+        .map(Bytes::from)
+}
+
+#[allow(clippy::result_large_err)]
+fn full<R: Representation>(
+    content: R,
+    content_type: ContentType,
+    etag: Option<ETag>,
+) -> Result {
+    let len = content.len();
+    let body = match len {
+        0 => body_from_stream(stream::empty::<Bytes>()),
+        len => body_from_stream(data(content, 0, len - 1)),
+    };
+
+    let mut res = response()
+        .typed_header(content_type)
+        .typed_header(ContentLength(len))
+        .typed_header(AcceptRanges::bytes());
+    if let Some(etag) = etag {
+        res = res.typed_header(etag);
+    }
+    res.body(body)
+}
+
+#[allow(clippy::result_large_err)]
+fn multipart<R: Representation>(
+    content: R,
+    content_type: ContentType,
+    etag: Option<ETag>,
+    ranges: Vec<(u64, u64)>,
+) -> Result {
+    let len = content.len();
+    let boundary = format!("{:016x}", rand::random::<u64>());
+
+    let part_header = {
+        let boundary = boundary.clone();
+        move |(start, end): (u64, u64)| {
+            Bytes::from(format!(
+                "--{boundary}\r\nContent-Type: {content_type}\r\n\
+                 Content-Range: bytes {start}-{end}/{len}\r\n\r\n"
+            ))
+        }
+    };
+    let closing = Bytes::from(format!("--{boundary}--\r\n"));
+
+    let content_length = ranges
+        .iter()
+        .map(|&range| part_header(range).len() as u64 + range.1 - range.0 + 3)
+        .sum::<u64>()
+        + closing.len() as u64;
+
+    let parts = stream::iter(ranges).flat_map(move |range| {
+        stream::once(future::ready(part_header(range)))
+            .chain(data(content.clone(), range.0, range.1))
+            .chain(stream::once(future::ready(Bytes::from_static(b"\r\n"))))
+    });
+
+    let mime = format!("multipart/byteranges; boundary={boundary}")
+        .parse::<mime::Mime>()
+        .map_err(|_| bad_request())?;
+
+    let mut res = response()
+        .status(StatusCode::PARTIAL_CONTENT)
+        .typed_header(ContentType::from(mime))
+        .typed_header(ContentLength(content_length))
+        .typed_header(AcceptRanges::bytes());
+    if let Some(etag) = etag {
+        res = res.typed_header(etag);
+    }
+    res.body(body_from_stream(
+        parts.chain(stream::iter(iter::once(closing))),
+    ))
+}
+
+/// Respond with `content`, honouring `Range` and `If-Range` request headers
+#[allow(clippy::result_large_err)]
+pub fn ranged<R: Representation>(
+    req: &Request,
+    content: R,
+    content_type: ContentType,
+    etag: Option<ETag>,
+) -> Result {
+    let len = content.len();
+
+    let range = req.headers().get(RANGE).and_then(|h| h.to_str().ok());
+    let fresh = match req.typed_header::<IfRange>() {
+        Some(if_range) => !if_range.is_modified(etag.as_ref(), None),
+        None => true,
+    };
+    let ranges = match range {
+        Some(range) if fresh => parse_ranges(range, len),
+        _ => None,
+    };
+
+    let mut ranges = match ranges {
+        Some(ranges) => ranges,
+        None => return full(content, content_type, etag),
+    };
+
+    match ranges.len() {
+        0 => response()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .typed_header(ContentRange::unsatisfied_bytes(len))
+            .typed_header(AcceptRanges::bytes())
+            .into(),
+        1 => {
+            let (start, end) = ranges.remove(0);
+            let mut res = response()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .typed_header(content_type)
+                .typed_header(ContentLength(end - start + 1))
+                .typed_header(
+                    ContentRange::bytes(start..=end, len)
+                        .map_err(|_| bad_request())?,
+                )
+                .typed_header(AcceptRanges::bytes());
+            if let Some(etag) = etag {
+                res = res.typed_header(etag);
+            }
+            res.body(body_from_stream(data(content, start, end)))
+        }
+        _ => multipart(content, content_type, etag, ranges),
+    }
+}
+
+pub async fn range(req: Request) -> Result {
+    let n = req
+        .param::<u32>("n")
+        .filter(|n| *n <= MAX_LEN)
+        .ok_or_else(bad_request)?;
+
+    let etag = format!("\"range{n}\"")
+        .parse::<ETag>()
+        .map_err(|_| bad_request())?;
+
+    ranged(
+        &req,
+        Alphabet(n.into()),
+        ContentType::octet_stream(),
+        Some(etag),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use hyper::header::{CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE};
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(parse_ranges("bytes=0-4", 10), Some(vec![(0, 4)]));
+        assert_eq!(parse_ranges("bytes=5-", 10), Some(vec![(5, 9)]));
+        assert_eq!(parse_ranges("bytes=-3", 10), Some(vec![(7, 9)]));
+        assert_eq!(parse_ranges("bytes=-30", 10), Some(vec![(0, 9)]));
+        assert_eq!(parse_ranges("bytes=8-20", 10), Some(vec![(8, 9)]));
+        assert_eq!(
+            parse_ranges("bytes=0-0, 2-3", 10),
+            Some(vec![(0, 0), (2, 3)])
+        );
+        assert_eq!(parse_ranges("bytes=10-", 10), Some(vec![]));
+        assert_eq!(
+            parse_ranges("bytes=6-7,0-4,2-5,9-", 10),
+            Some(vec![(0, 7), (9, 9)])
+        );
+        assert_eq!(parse_ranges("bytes=0-9,0-9,-10", 10), Some(vec![(0, 9)]));
+        let many = vec!["0-0"; MAX_RANGES + 1].join(",");
+        assert_eq!(parse_ranges(&format!("bytes={many}"), 10), None);
+        assert_eq!(parse_ranges("bytes=5-4", 10), None);
+        assert_eq!(parse_ranges("items=0-4", 10), None);
+        assert_eq!(parse_ranges("bytes=a-b", 10), None);
+    }
+
+    #[tokio::test]
+    async fn test_range_full() {
+        let res = request().param("n", "30").handle(range).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["accept-ranges"], "bytes");
+        assert_eq!(res.headers()[ETAG], "\"range30\"");
+        assert_eq!(
+            res.read_body_utf8().await.unwrap(),
+            "abcdefghijklmnopqrstuvwxyzabcd"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_range_single() {
+        let res = request()
+            .param("n", "30")
+            .header(RANGE, "bytes=24-27")
+            .handle(range)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers()[CONTENT_RANGE], "bytes 24-27/30");
+        assert_eq!(res.read_body_utf8().await.unwrap(), "yzab");
+    }
+
+    #[tokio::test]
+    async fn test_range_multiple() {
+        let res = request()
+            .param("n", "30")
+            .header(RANGE, "bytes=0-1,-2")
+            .handle(range)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = res.headers()[CONTENT_TYPE].to_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_owned();
+        let length = res.headers()["content-length"].clone();
+
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(length, body.len().to_string().as_str());
+        assert_eq!(
+            body,
+            format!(
+                "--{boundary}\r\n\
+                 Content-Type: application/octet-stream\r\n\
+                 Content-Range: bytes 0-1/30\r\n\r\nab\r\n\
+                 --{boundary}\r\n\
+                 Content-Type: application/octet-stream\r\n\
+                 Content-Range: bytes 28-29/30\r\n\r\ncd\r\n\
+                 --{boundary}--\r\n"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_range_too_long() {
+        let res = request().param("n", "102401").handle(range).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_range_unsatisfiable() {
+        let res = request()
+            .param("n", "30")
+            .header(RANGE, "bytes=30-40")
+            .handle(range)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(res.headers()[CONTENT_RANGE], "bytes */30");
+    }
+
+    #[tokio::test]
+    async fn test_range_if_range() {
+        let res = request()
+            .param("n", "30")
+            .header(RANGE, "bytes=0-1")
+            .header(IF_RANGE, "\"range30\"")
+            .handle(range)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+
+        let res = request()
+            .param("n", "30")
+            .header(RANGE, "bytes=0-1")
+            .header(IF_RANGE, "\"stale\"")
+            .handle(range)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.read_body().await.unwrap().len(), 30);
+    }
+}
@@ -1,7 +1,9 @@
-mod uri;
+pub(crate) mod uri;
 
 use self::uri::absolute_url;
-use crate::http::{Request, Result, bad_request, redirect_to};
+use crate::http::{
+    Error, Request, Result, StatusCode, bad_request, not_found, redirect_with,
+};
 use hyper::Uri;
 use serde_derive::Deserialize;
 use std::cmp::min;
@@ -9,6 +11,7 @@ use std::cmp::min;
 #[derive(Deserialize)]
 pub struct RedirectUrlParams {
     url: String,
+    status_code: Option<u16>,
 }
 
 pub async fn to(req: Request) -> Result {
@@ -16,37 +19,69 @@ pub async fn to(req: Request) -> Result {
         .query::<RedirectUrlParams>()
         .map_err(|_| bad_request())?;
 
+    let status = match query.status_code {
+        Some(code) => StatusCode::from_u16(code)
+            .ok()
+            .filter(StatusCode::is_redirection)
+            .ok_or_else(bad_request)?,
+        None => StatusCode::FOUND,
+    };
     let uri = query.url.parse::<Uri>().map_err(|_| bad_request())?;
 
-    redirect_to(uri)
+    redirect_with(status, uri)
 }
 
-pub async fn redirect(req: Request) -> Result {
-    relative(req).await
+/// The status given by the `/{n}/{status}` variants of the redirect chains
+#[allow(clippy::result_large_err)]
+fn status(req: &Request) -> std::result::Result<Option<StatusCode>, Error> {
+    let Some(status) = req.param::<String>("status") else {
+        return Ok(None);
+    };
+    match status.parse::<u16>() {
+        Ok(code @ (301 | 302 | 303 | 307 | 308)) => {
+            Ok(StatusCode::from_u16(code).ok())
+        }
+        _ => Err(not_found()),
+    }
 }
 
-pub async fn relative(req: Request) -> Result {
-    let n = req.param::<u16>("n").ok_or_else(bad_request)?;
+/// The status and path of the next redirect in a chain of n
+#[allow(clippy::result_large_err)]
+fn next(
+    req: &Request,
+    prefix: &str,
+) -> std::result::Result<(StatusCode, String), Error> {
+    let n = req
+        .param::<u16>("n")
+        .filter(|n| *n > 0)
+        .ok_or_else(bad_request)?;
     let n = min(n - 1, 100);
 
-    let url = if n > 0 {
-        format!("/relative-redirect/{n}")
-    } else {
-        String::from("/")
+    let status = status(req)?;
+    let url = match (n, status) {
+        (0, None) => String::from("/"),
+        // The chain ends where the method and body that made it through show
+        (0, Some(_)) => String::from("/anything"),
+        (n, None) => format!("/{prefix}/{n}"),
+        (n, Some(status)) => format!("/{prefix}/{n}/{}", status.as_u16()),
     };
 
+    Ok((status.unwrap_or(StatusCode::FOUND), url))
+}
+
+pub async fn redirect(req: Request) -> Result {
+    relative(req).await
+}
+
+pub async fn relative(req: Request) -> Result {
+    let (status, url) = next(&req, "relative-redirect")?;
+
     let uri = url.parse::<Uri>().map_err(|_| bad_request())?;
-    redirect_to(uri)
+    redirect_with(status, uri)
 }
 
 pub async fn absolute(req: Request) -> Result {
-    let n = req.param::<u16>("n").ok_or_else(bad_request)?;
-    let n = min(n - 1, 100);
-    let url = if n > 0 {
-        format!("/absolute-redirect/{n}")
-    } else {
-        String::from("/")
-    };
+    let (status, url) = next(&req, "absolute-redirect")?;
 
     let request_uri = req.uri();
     let response_uri = absolute_url(&req, request_uri)
@@ -54,7 +89,7 @@ pub async fn absolute(req: Request) -> Result {
         .and_then(|url| Ok(url.to_string().parse::<Uri>()?))
         .map_err(|_| bad_request())?;
 
-    redirect_to(response_uri)
+    redirect_with(status, response_uri)
 }
 
 #[cfg(test)]
@@ -65,7 +100,7 @@ mod test {
     use crate::headers::Host;
     use crate::headers::Location;
     use crate::test::*;
-    use hyper::http::StatusCode;
+    use hyper::http::{Method, StatusCode};
     use hyper::http::{Uri, uri::Authority};
 
     #[tokio::test]
@@ -83,6 +118,31 @@ mod test {
         )
     }
 
+    #[tokio::test]
+    async fn test_redirect_to_status() {
+        let res = request()
+            .path("/?url=/anything&status_code=307")
+            .handle(to)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            res.headers().typed_get::<Location>().unwrap().uri(),
+            &Uri::from_static("/anything")
+        );
+
+        for code in ["200", "404", "1000"] {
+            let res = request()
+                .path(&format!("/?url=/anything&status_code={code}"))
+                .handle(to)
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{code}");
+        }
+    }
+
     #[tokio::test]
     async fn test_redirect() {
         let res = request().param("n", "5").handle(redirect).await.unwrap();
@@ -127,6 +187,47 @@ mod test {
         )
     }
 
+    #[tokio::test]
+    async fn test_relative_redirect_status() {
+        for (n, status, location) in [
+            ("3", "308", "/relative-redirect/2/308"),
+            ("1", "303", "/anything"),
+        ] {
+            let res = request()
+                .method(Method::POST)
+                .param("n", n)
+                .param("status", status)
+                .handle(relative)
+                .await
+                .unwrap();
+
+            assert_eq!(res.status().as_str(), status);
+            assert_eq!(
+                res.headers().typed_get::<Location>().unwrap().uri(),
+                &Uri::from_static(location)
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirect_unknown_status() {
+        let res = request()
+            .param("n", "3")
+            .param("status", "304")
+            .handle(relative)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_zero() {
+        let res = request().param("n", "0").handle(relative).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn test_absolute_redirect() {
         let res = request()
@@ -143,6 +244,23 @@ mod test {
         )
     }
 
+    #[tokio::test]
+    async fn test_absolute_redirect_status() {
+        let res = request()
+            .typed_header(Host::from(Authority::from_static("example.com")))
+            .param("n", "5")
+            .param("status", "301")
+            .handle(absolute)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            res.headers().typed_get::<Location>().unwrap().uri(),
+            &Uri::from_static("http://example.com/absolute-redirect/4/301")
+        )
+    }
+
     #[tokio::test]
     async fn test_absolute_redirect_last() {
         let res = request()
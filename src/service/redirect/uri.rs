@@ -1,6 +1,7 @@
 use crate::headers::Host;
 use crate::http::Request;
 use crate::http::Uri;
+use hyper::http::uri::Scheme;
 use std::env;
 use std::sync::LazyLock;
 use url::Url;
@@ -8,9 +9,9 @@ use url::Url;
 static BASE_URL: LazyLock<Option<Url>> =
     LazyLock::new(|| env::var_os("BASE_URL")?.into_string().ok()?.parse().ok());
 
-fn host_to_url(host: &str) -> anyhow::Result<Url> {
+fn host_to_url(scheme: &Scheme, host: &str) -> anyhow::Result<Url> {
     Ok(Uri::builder()
-        .scheme("http") // FIXME: Determine protocol
+        .scheme(scheme.clone())
         .authority(host)
         .path_and_query("/")
         .build()?
@@ -19,12 +20,14 @@ fn host_to_url(host: &str) -> anyhow::Result<Url> {
 }
 
 fn host_from_headers(req: &Request) -> anyhow::Result<Url> {
+    // HTTP/2 requests carry the host in the :authority pseudo-header instead
     let host = req
         .typed_header::<Host>()
-        .ok_or_else(|| anyhow::anyhow!("no host header found"))?
-        .to_string();
+        .map(|host| host.to_string())
+        .or_else(|| Some(req.uri().authority()?.to_string()))
+        .ok_or_else(|| anyhow::anyhow!("no host header found"))?;
 
-    host_to_url(&host)
+    host_to_url(req.scheme(), &host)
 }
 
 fn absolute_uri(req: &Request, uri: &Uri) -> anyhow::Result<Uri> {
@@ -49,19 +52,32 @@ mod test {
     use super::{absolute_uri, host_from_headers, host_to_url};
     use crate::headers::Host;
     use crate::test::*;
+    use hyper::http::uri::Scheme;
     use hyper::http::{Uri, uri::Authority};
 
     #[test]
     fn test_host_to_url() {
         assert_eq!(
-            host_to_url("example.com").unwrap().to_string(),
+            host_to_url(&Scheme::HTTP, "example.com")
+                .unwrap()
+                .to_string(),
             "http://example.com/",
         )
     }
 
+    #[test]
+    fn test_host_to_url_https() {
+        assert_eq!(
+            host_to_url(&Scheme::HTTPS, "example.com")
+                .unwrap()
+                .to_string(),
+            "https://example.com/",
+        )
+    }
+
     #[test]
     fn test_host_to_url_parse_error() {
-        assert!(host_to_url("a/b/c").is_err())
+        assert!(host_to_url(&Scheme::HTTP, "a/b/c").is_err())
     }
 
     #[test]
@@ -90,6 +106,29 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_host_from_headers_https() {
+        let req = request()
+            .scheme(Scheme::HTTPS)
+            .typed_header(Host::from(Authority::from_static("example.com")))
+            .build();
+
+        assert_eq!(
+            host_from_headers(&req).unwrap().to_string(),
+            "https://example.com/"
+        )
+    }
+
+    #[test]
+    fn test_host_from_authority() {
+        let req = request().path("https://example.com/foo").build();
+
+        assert_eq!(
+            host_from_headers(&req).unwrap().to_string(),
+            "https://example.com/"
+        )
+    }
+
     #[test]
     fn test_host_from_headers_no_header() {
         let req = request().build();
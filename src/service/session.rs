@@ -0,0 +1,429 @@
+use super::cookies::flag;
+use crate::headers::{Cookie, HeaderMapExt, Location, SetCookie};
+use crate::http::{
+    Error, Request, Result, StatusCode, Uri, bad_request, json, json_error,
+    response,
+};
+use cookie::time::Duration;
+use cookie::{Cookie as HTTPCookie, CookieJar, Key, SameSite};
+use ring::digest::{SHA256, digest};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_USER: &str = "user";
+const SIGNED: &str = "session";
+const ENCRYPTED: &str = "encrypted_session";
+/// The base64 encoded HMAC-SHA256 prefixing signed cookie values
+const SIGNATURE_LEN: usize = 44;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Signed,
+    Encrypted,
+}
+
+impl Mode {
+    fn cookie_name(self) -> &'static str {
+        match self {
+            Mode::Signed => SIGNED,
+            Mode::Encrypted => ENCRYPTED,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Mode::Signed => "signed",
+            Mode::Encrypted => "encrypted",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SessionData {
+    id: String,
+    user: String,
+    issued_at: u64,
+    expires_at: Option<u64>,
+}
+
+impl SessionData {
+    fn new(user: String, expires_in: Option<i64>) -> Self {
+        let issued_at = now();
+        SessionData {
+            id: format!("{:032x}", rand::random::<u128>()),
+            user,
+            issued_at,
+            expires_at: expires_in.map(|s| issued_at.saturating_add_signed(s)),
+        }
+    }
+}
+
+/// A 401 explaining why the session was rejected
+fn unauthorized(reason: &str) -> Error {
+    json_error(StatusCode::UNAUTHORIZED, &json!({ "error": reason }))
+}
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    user: Option<String>,
+    mode: Option<String>,
+    /// Seconds until the session expires, a browser session when unset
+    expires_in: Option<i64>,
+    redirect: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RedirectQuery {
+    redirect: Option<String>,
+}
+
+fn whoami_location() -> Location {
+    Location::from(Uri::from_static("/session/whoami"))
+}
+
+/// Whether to follow up with a redirect to `/session/whoami`
+#[allow(clippy::result_large_err)]
+fn redirect(value: Option<&str>) -> std::result::Result<bool, Error> {
+    value.map_or(Ok(false), |v| flag(v).ok_or_else(bad_request))
+}
+
+/// Cookie sessions, signed or encrypted with the server key
+#[derive(Clone)]
+pub struct Session(Arc<Key>);
+
+impl Session {
+    /// Derive the key from a secret of any length
+    pub fn new(secret: &[u8]) -> Self {
+        Session(Arc::new(Key::derive_from(digest(&SHA256, secret).as_ref())))
+    }
+
+    pub fn generate() -> Self {
+        Session(Arc::new(Key::generate()))
+    }
+
+    fn cookie(&self, mode: Mode, data: &SessionData) -> HTTPCookie<'static> {
+        // Form encoding keeps signed sessions readable, in valid cookie octets
+        let value = serde_urlencoded::to_string(data).unwrap_or_default();
+        let mut cookie = HTTPCookie::build((mode.cookie_name(), value))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .build();
+        if let Some(expires_at) = data.expires_at {
+            let max_age = expires_at.saturating_sub(data.issued_at);
+            cookie.set_max_age(Duration::seconds(max_age as i64));
+        }
+
+        let mut jar = CookieJar::new();
+        match mode {
+            Mode::Signed => jar.signed_mut(&self.0).add(cookie),
+            Mode::Encrypted => jar.private_mut(&self.0).add(cookie),
+        }
+        jar.delta().next().cloned().expect("cookie was just added")
+    }
+
+    fn removal(mode: Mode) -> HTTPCookie<'static> {
+        let mut cookie = HTTPCookie::build((mode.cookie_name(), ""))
+            .path("/")
+            .build();
+        cookie.make_removal();
+        cookie
+    }
+
+    /// The session presented by the client, or why it was rejected
+    #[allow(clippy::result_large_err)]
+    fn verify(
+        &self,
+        req: &Request,
+    ) -> std::result::Result<(Mode, SessionData), Error> {
+        let cookies = req.typed_header::<Cookie>().unwrap_or(Cookie(vec![]));
+        let find = |mode: Mode| {
+            let name = mode.cookie_name();
+            let cookie = cookies.iter().find(|c| c.name() == name)?;
+            Some((mode, cookie.clone().into_owned()))
+        };
+        let (mode, cookie) = find(Mode::Signed)
+            .or_else(|| find(Mode::Encrypted))
+            .ok_or_else(|| {
+                unauthorized("there is no session cookie, log in first")
+            })?;
+
+        let jar = CookieJar::new();
+        let verified = match mode {
+            Mode::Signed if cookie.value().len() <= SIGNATURE_LEN => {
+                return Err(unauthorized("the session cookie is not signed"));
+            }
+            Mode::Signed => {
+                jar.signed(&self.0).verify(cookie).ok_or_else(|| {
+                    unauthorized(
+                        "the signature does not match, the session cookie was \
+                     tampered with or signed with another key",
+                    )
+                })?
+            }
+            Mode::Encrypted => {
+                jar.private(&self.0).decrypt(cookie).ok_or_else(|| {
+                    unauthorized(
+                        "the session cookie could not be decrypted, it was \
+                         tampered with or encrypted with another key",
+                    )
+                })?
+            }
+        };
+
+        let data = serde_urlencoded::from_str::<SessionData>(verified.value())
+            .map_err(|_| unauthorized("the session data is malformed"))?;
+        if data.expires_at.is_some_and(|exp| exp <= now()) {
+            return Err(unauthorized("the session has expired"));
+        }
+        Ok((mode, data))
+    }
+
+    /// The session as JSON, setting its cookies and redirecting to
+    /// `/session/whoami` when asked for
+    #[allow(clippy::result_large_err)]
+    fn respond(
+        mode: Mode,
+        data: &SessionData,
+        cookies: Vec<HTTPCookie<'static>>,
+        redirect: bool,
+    ) -> Result {
+        if redirect {
+            return response()
+                .status(StatusCode::FOUND)
+                .typed_header(whoami_location())
+                .typed_header(SetCookie(cookies))
+                .into();
+        }
+
+        let mut body = serde_json::to_value(data).unwrap_or(Value::Null);
+        body["mode"] = mode.as_str().into();
+        let mut res = json(&body)?;
+        if !cookies.is_empty() {
+            res.headers_mut().typed_insert(SetCookie(cookies));
+        }
+        Ok(res)
+    }
+
+    /// Start a session, replacing one of the other mode
+    pub async fn login(self, req: Request) -> Result {
+        let query = req.query::<LoginQuery>().map_err(|_| bad_request())?;
+        let mode = match query.mode.as_deref() {
+            None | Some("signed") => Mode::Signed,
+            Some("encrypted") => Mode::Encrypted,
+            Some(_) => return Err(bad_request()),
+        };
+        let redirect = redirect(query.redirect.as_deref())?;
+
+        let user = query.user.unwrap_or_else(|| String::from(DEFAULT_USER));
+        let data = SessionData::new(user, query.expires_in);
+        let other = match mode {
+            Mode::Signed => Mode::Encrypted,
+            Mode::Encrypted => Mode::Signed,
+        };
+        let cookies = vec![self.cookie(mode, &data), Self::removal(other)];
+
+        Self::respond(mode, &data, cookies, redirect)
+    }
+
+    pub async fn whoami(self, req: Request) -> Result {
+        let (mode, data) = self.verify(&req)?;
+        Self::respond(mode, &data, vec![], false)
+    }
+
+    /// Issue a new session for the same user, which keeps its expiry
+    pub async fn rotate(self, req: Request) -> Result {
+        let query = req.query::<RedirectQuery>().map_err(|_| bad_request())?;
+        let redirect = redirect(query.redirect.as_deref())?;
+        let (mode, old) = self.verify(&req)?;
+
+        let expires_in = old.expires_at.map(|exp| {
+            i64::try_from(exp.saturating_sub(now())).unwrap_or(i64::MAX)
+        });
+        let data = SessionData::new(old.user, expires_in);
+        let cookies = vec![self.cookie(mode, &data)];
+
+        Self::respond(mode, &data, cookies, redirect)
+    }
+
+    pub async fn logout(self, req: Request) -> Result {
+        let query = req.query::<RedirectQuery>().map_err(|_| bad_request())?;
+        let redirect = redirect(query.redirect.as_deref())?;
+
+        let cookies =
+            vec![Self::removal(Mode::Signed), Self::removal(Mode::Encrypted)];
+        let mut res = response().typed_header(SetCookie(cookies));
+        if redirect {
+            res = res
+                .status(StatusCode::FOUND)
+                .typed_header(whoami_location());
+        }
+        res.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::handler::with_state;
+    use crate::test::*;
+
+    type Response = hyper::Response<crate::http::Body>;
+
+    async fn login(session: &Session, query: &str) -> Response {
+        request()
+            .path(&format!("/?{query}"))
+            .handle(with_state(session.clone(), Session::login))
+            .await
+            .unwrap()
+    }
+
+    /// The session cookie set by a response, as sent back by a client
+    fn session_cookie(res: &Response) -> HTTPCookie<'static> {
+        let cookies = res.headers().typed_get::<SetCookie>().unwrap();
+        let cookie = cookies.iter().find(|c| !c.value().is_empty()).unwrap();
+        HTTPCookie::new(cookie.name().to_owned(), cookie.value().to_owned())
+    }
+
+    async fn whoami(
+        session: &Session,
+        cookie: HTTPCookie<'static>,
+    ) -> Response {
+        request()
+            .typed_header(Cookie(vec![cookie]))
+            .handle(with_state(session.clone(), Session::whoami))
+            .await
+            .unwrap()
+    }
+
+    async fn assert_rejected(res: Response, reason: &str) {
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["error"], reason);
+    }
+
+    #[tokio::test]
+    async fn test_login_and_whoami() {
+        let session = Session::new(b"secret");
+        for mode in ["signed", "encrypted"] {
+            let res = login(&session, &format!("user=alice&mode={mode}")).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let cookie = session_cookie(&res);
+            if mode == "signed" {
+                assert!(cookie.value().contains("alice"));
+            } else {
+                assert!(!cookie.value().contains("alice"));
+            }
+
+            let res = whoami(&session, cookie).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.read_body_json().await.unwrap();
+            assert_eq!(body["user"], "alice");
+            assert_eq!(body["mode"], mode);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_redirect() {
+        let res = login(&Session::generate(), "redirect").await;
+
+        assert_eq!(res.status(), StatusCode::FOUND);
+        assert_eq!(res.headers()["location"], "/session/whoami");
+        assert_eq!(session_cookie(&res).name(), SIGNED);
+    }
+
+    #[tokio::test]
+    async fn test_no_session() {
+        let res = request()
+            .handle(with_state(Session::generate(), Session::whoami))
+            .await
+            .unwrap();
+
+        assert_rejected(res, "there is no session cookie, log in first").await;
+    }
+
+    #[tokio::test]
+    async fn test_tampered_session() {
+        let session = Session::generate();
+
+        let cookie = session_cookie(&login(&session, "user=alice").await);
+        let tampered = cookie.value().replace("alice", "admin");
+        let res = whoami(&session, HTTPCookie::new(SIGNED, tampered)).await;
+        assert_rejected(
+            res,
+            "the signature does not match, the session cookie was tampered \
+             with or signed with another key",
+        )
+        .await;
+
+        let res = whoami(&session, HTTPCookie::new(SIGNED, "{}")).await;
+        assert_rejected(res, "the session cookie is not signed").await;
+
+        let res = login(&session, "mode=encrypted").await;
+        let cookie = session_cookie(&res);
+        let res = whoami(&Session::new(b"other"), cookie).await;
+        assert_rejected(
+            res,
+            "the session cookie could not be decrypted, it was tampered with \
+             or encrypted with another key",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_expired_session() {
+        let session = Session::generate();
+        let cookie = session_cookie(&login(&session, "expires_in=-1").await);
+
+        let res = whoami(&session, cookie).await;
+        assert_rejected(res, "the session has expired").await;
+    }
+
+    #[tokio::test]
+    async fn test_rotate() {
+        let session = Session::generate();
+        let res = login(&session, "user=alice&mode=encrypted").await;
+        let cookie = session_cookie(&res);
+        let old = res.read_body_json().await.unwrap();
+
+        let res = request()
+            .typed_header(Cookie(vec![cookie]))
+            .handle(with_state(session.clone(), Session::rotate))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookie = session_cookie(&res);
+        let rotated = res.read_body_json().await.unwrap();
+        assert_ne!(rotated["id"], old["id"]);
+
+        let res = whoami(&session, cookie).await;
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body["id"], rotated["id"]);
+        assert_eq!(body["user"], "alice");
+        assert_eq!(body["mode"], "encrypted");
+    }
+
+    #[tokio::test]
+    async fn test_logout() {
+        let res = request()
+            .handle(with_state(Session::generate(), Session::logout))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookies = res.headers().typed_get::<SetCookie>().unwrap();
+        assert_eq!(
+            cookies.iter().map(|c| c.name()).collect::<Vec<_>>(),
+            [SIGNED, ENCRYPTED]
+        );
+        assert!(cookies.iter().all(|c| c.max_age() == Some(Duration::ZERO)));
+    }
+}
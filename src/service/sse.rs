@@ -0,0 +1,224 @@
+use crate::headers::{CacheControl, ContentType, LastEventId};
+use crate::http::{
+    Request, Result, StatusCode, bad_request, body_from_stream, response,
+};
+use futures::prelude::*;
+use futures_timer::Delay;
+use serde_derive::Deserialize;
+use std::cmp::min;
+use std::fmt::Write;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+pub struct EventParams {
+    count: Option<u64>,
+    interval: Option<u64>,
+    event: Option<String>,
+    id: Option<bool>,
+    retry: Option<u64>,
+    close_after: Option<u64>,
+}
+
+struct EventFormat {
+    event: Option<String>,
+    id: bool,
+    retry: Option<u64>,
+}
+
+impl EventFormat {
+    fn format(&self, id: u64, first: bool) -> String {
+        let mut output = String::new();
+        if let (true, Some(retry)) = (first, self.retry) {
+            let _ = writeln!(output, "retry: {retry}");
+        }
+        if self.id {
+            let _ = writeln!(output, "id: {id}");
+        }
+        if let Some(event) = &self.event {
+            let _ = writeln!(output, "event: {event}");
+        }
+        let _ = writeln!(output, "data: {id}");
+        output.push('\n');
+        output
+    }
+}
+
+pub async fn sse(req: Request) -> Result {
+    let query = req.query::<EventParams>().map_err(|_| bad_request())?;
+
+    if query.event.iter().any(|event| event.contains(['\r', '\n'])) {
+        return Err(bad_request());
+    }
+
+    let count = min(query.count.unwrap_or(10), 100);
+    let interval =
+        Duration::from_millis(min(query.interval.unwrap_or(1000), 10_000));
+
+    // Resume after the last event the client saw
+    let start = match req.typed_header::<LastEventId>() {
+        Some(last) => {
+            let last = last.id().parse::<u64>().map_err(|_| bad_request())?;
+            last.saturating_add(1)
+        }
+        None => 1,
+    };
+
+    // A 204 tells EventSource clients to stop reconnecting
+    if start > count {
+        return response().status(StatusCode::NO_CONTENT).into();
+    }
+
+    let end = match query.close_after {
+        Some(close_after) => {
+            min(count, (start - 1).saturating_add(close_after))
+        }
+        None => count,
+    };
+
+    let format = EventFormat {
+        event: query.event,
+        id: query.id.unwrap_or(true),
+        retry: query.retry,
+    };
+
+    let events = stream::iter(start..=end).then(move |id| {
+        let output = format.format(id, id == start);
+        async move {
+            if id != start {
+                let _ = Delay::new(interval).await;
+            }
+            output
+        }
+    });
+
+    response()
+        .typed_header(ContentType::from(mime::TEXT_EVENT_STREAM))
+        .typed_header(CacheControl::new().with_no_cache())
+        .body(body_from_stream(Box::pin(events)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::headers::HeaderMapExt;
+    use crate::test::*;
+
+    #[tokio::test]
+    async fn test_sse() {
+        let res = request()
+            .path("/?count=3&interval=0")
+            .handle(sse)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().typed_get::<ContentType>().unwrap(),
+            ContentType::from(mime::TEXT_EVENT_STREAM)
+        );
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(
+            body,
+            "id: 1\ndata: 1\n\nid: 2\ndata: 2\n\nid: 3\ndata: 3\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sse_event_and_retry() {
+        let res = request()
+            .path("/?count=2&interval=0&event=tick&retry=500&id=false")
+            .handle(sse)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(
+            body,
+            "retry: 500\nevent: tick\ndata: 1\n\nevent: tick\ndata: 2\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sse_last_event_id() {
+        let res = request()
+            .path("/?count=3&interval=0")
+            .typed_header(LastEventId(String::from("2")))
+            .handle(sse)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(body, "id: 3\ndata: 3\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_sse_close_after() {
+        let res = request()
+            .path("/?count=5&interval=0&close_after=2")
+            .typed_header(LastEventId(String::from("1")))
+            .handle(sse)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(body, "id: 2\ndata: 2\n\nid: 3\ndata: 3\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_sse_exhausted() {
+        let res = request()
+            .path("/?count=3&interval=0")
+            .typed_header(LastEventId(String::from("3")))
+            .handle(sse)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_sse_huge_last_event_id_and_close_after() {
+        let res = request()
+            .path("/?count=3&interval=0")
+            .typed_header(LastEventId(u64::MAX.to_string()))
+            .handle(sse)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = request()
+            .path(&format!("/?count=3&interval=0&close_after={}", u64::MAX))
+            .typed_header(LastEventId(String::from("1")))
+            .handle(sse)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(body, "id: 2\ndata: 2\n\nid: 3\ndata: 3\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_sse_bad_last_event_id() {
+        let res = request()
+            .typed_header(LastEventId(String::from("abc")))
+            .handle(sse)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_sse_bad_event_name() {
+        let res = request()
+            .path("/?event=a%0Adata:%20injected")
+            .handle(sse)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,15 +1,126 @@
-use crate::http::{Request, Result, StatusCode, bad_request, response};
+use crate::headers::{
+    Challenge, ContentType, Location, RetryAfter, WWWAuthenticate,
+};
+use crate::http::{Request, Result, StatusCode, Uri, bad_request, response};
+use crate::random::rng;
+use hyper::ext::ReasonPhrase;
+use rand::distr::Distribution;
+use rand::distr::weighted::WeightedIndex;
+use serde_derive::Deserialize;
+use std::time::Duration;
+
+const DEFAULT_RETRY_AFTER: u64 = 1;
+
+const TEAPOT: &str = r#"
+    -=[ teapot ]=-
+
+       _...._
+     .'  _ _ `.
+    | ."` ^ `". _,
+    \_;`"---"`|//
+      |       ;/
+      \_     _/
+        `"""`
+"#;
+
+#[derive(Deserialize)]
+pub struct StatusQueryParams {
+    seed: Option<u32>,
+    /// Seconds for `Retry-After` on 429 and 503
+    retry_after: Option<u64>,
+    /// Replaces the canonical reason phrase on HTTP/1.1
+    reason: Option<String>,
+}
+
+/// Parse `code` or a weighted choice of codes, `200:0.9,500:0.1`, where
+/// codes without a weight weigh 1 and weights must add up to a finite total
+fn parse_codes(codes: &str) -> Option<(Vec<StatusCode>, Vec<f64>)> {
+    let (statuses, weights): (Vec<_>, Vec<_>) = codes
+        .split(',')
+        .map(|choice| {
+            let (code, weight) = match choice.split_once(':') {
+                Some((code, weight)) => (code, weight.parse::<f64>().ok()?),
+                None => (choice, 1.0),
+            };
+            if !weight.is_finite() {
+                return None;
+            }
+            Some((code.trim().parse::<StatusCode>().ok()?, weight))
+        })
+        .collect::<Option<_>>()?;
+
+    weights
+        .iter()
+        .sum::<f64>()
+        .is_finite()
+        .then_some((statuses, weights))
+}
 
 pub async fn status_code(req: Request) -> Result {
-    let status = req.param::<StatusCode>("code").ok_or_else(bad_request)?;
+    let codes = req.param::<String>("code").ok_or_else(bad_request)?;
+    let query = req
+        .query::<StatusQueryParams>()
+        .map_err(|_| bad_request())?;
 
-    response().status(status).into()
+    let (statuses, weights) = parse_codes(&codes).ok_or_else(bad_request)?;
+    // hyper turns a final 1xx into a 500, so refuse any choice of one
+    // rather than depending on the seed
+    if statuses.iter().any(StatusCode::is_informational) {
+        return response()
+            .status(StatusCode::BAD_REQUEST)
+            .typed_header(ContentType::text())
+            .body("1xx statuses can only precede a final response");
+    }
+    let status = match statuses.as_slice() {
+        [status] => *status,
+        _ => {
+            let choice =
+                WeightedIndex::new(&weights).map_err(|_| bad_request())?;
+            statuses[choice.sample(&mut rng(query.seed))]
+        }
+    };
+
+    let reason = match query.reason {
+        Some(reason) => {
+            Some(ReasonPhrase::try_from(reason).map_err(|_| bad_request())?)
+        }
+        None => None,
+    };
+
+    let mut res = extras(status, query.retry_after)?;
+    if let Some(reason) = reason {
+        res.extensions_mut().insert(reason);
+    }
+    Ok(res)
+}
+
+/// Headers and bodies making the status look like the real thing
+#[allow(clippy::result_large_err)]
+fn extras(status: StatusCode, retry_after: Option<u64>) -> Result {
+    let res = response().status(status);
+    match status.as_u16() {
+        429 | 503 => {
+            let seconds = retry_after.unwrap_or(DEFAULT_RETRY_AFTER);
+            res.typed_header(RetryAfter::delay(Duration::from_secs(seconds)))
+                .into()
+        }
+        // Somewhere that takes any method, for clients that keep it
+        300..=399 if status != StatusCode::NOT_MODIFIED => res
+            .typed_header(Location::from(Uri::from_static("/anything")))
+            .into(),
+        401 => res
+            .typed_header(WWWAuthenticate::from(Challenge::basic("Fake Realm")))
+            .into(),
+        418 => res.typed_header(ContentType::text()).body(TEAPOT),
+        _ => res.into(),
+    }
 }
 
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use crate::headers::HeaderMapExt;
     use crate::test::*;
     use hyper::http::StatusCode;
 
@@ -35,4 +146,128 @@ mod test {
 
         assert_eq!(res.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[tokio::test]
+    async fn test_non_standard_status_code() {
+        let res = request()
+            .path("/?reason=Totally%20Fine")
+            .param("code", "299")
+            .handle(status_code)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status().as_u16(), 299);
+        let reason = res.extensions().get::<ReasonPhrase>().unwrap();
+        assert_eq!(reason.as_bytes(), b"Totally Fine");
+    }
+
+    #[tokio::test]
+    async fn test_bad_reason() {
+        let res = request()
+            .path("/?reason=%0A")
+            .param("code", "200")
+            .handle(status_code)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    /// Deliberately unlike httpbin, which answers with a bare 1xx
+    #[tokio::test]
+    async fn test_informational_status_code_rejected() {
+        for code in ["100", "103", "199"] {
+            let res = request()
+                .param("code", code)
+                .handle(status_code)
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{code}");
+            let body = res.read_body_utf8().await.unwrap();
+            assert!(body.contains("1xx"), "{code}");
+        }
+
+        for seed in (0..20).map(|i| i * 100_003) {
+            let status = weighted("100:1,200:1", seed).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{seed}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bad_weights() {
+        for code in [
+            "200:x",
+            "200:0,500:0",
+            "200:-1,500:1",
+            "200,",
+            "200:inf,500:1",
+            "200:NaN,500:1",
+            "200:1e308,500:1e308",
+        ] {
+            let res = request()
+                .param("code", code)
+                .handle(status_code)
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{code}");
+        }
+    }
+
+    async fn weighted(code: &str, seed: u32) -> StatusCode {
+        request()
+            .path(&format!("/?seed={seed}"))
+            .param("code", code)
+            .handle(status_code)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_weighted_status_codes() {
+        let mut seen = vec![];
+        for seed in (0..50).map(|i| i * 100_003) {
+            let status = weighted("200:0.5,500:0.5", seed).await;
+            assert_eq!(status, weighted("200:0.5,500:0.5", seed).await);
+            seen.push(status);
+        }
+        assert!(seen.contains(&StatusCode::OK));
+        assert!(seen.contains(&StatusCode::INTERNAL_SERVER_ERROR));
+
+        for seed in 0..10 {
+            let status = weighted("200:0,503:1", seed).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status_extras() {
+        let res = request()
+            .path("/?retry_after=30")
+            .param("code", "503")
+            .handle(status_code)
+            .await
+            .unwrap();
+        assert_eq!(res.headers()["retry-after"], "30");
+
+        let res = request().param("code", "307").handle(status_code).await;
+        let res = res.unwrap();
+        assert_eq!(
+            res.headers().typed_get::<Location>().unwrap().uri(),
+            &Uri::from_static("/anything")
+        );
+
+        let res = request().param("code", "401").handle(status_code).await;
+        let challenge = res.unwrap().headers().typed_get::<WWWAuthenticate>();
+        assert_eq!(
+            challenge.unwrap(),
+            WWWAuthenticate::basic_realm("Fake Realm")
+        );
+
+        let res = request().param("code", "418").handle(status_code).await;
+        let body = res.unwrap().read_body_utf8().await.unwrap();
+        assert!(body.contains("teapot"));
+    }
 }
@@ -0,0 +1,42 @@
+use crate::handler::Handler;
+use crate::http::{Bytes, Request, Result, response};
+use async_trait::async_trait;
+use hyper::header::CONTENT_TYPE;
+
+const PEM_CONTENT_TYPE: &str = "application/x-pem-file";
+
+#[derive(Debug, Clone)]
+pub struct CaCertificate(Bytes);
+
+#[async_trait]
+impl Handler for CaCertificate {
+    async fn handle(&self, _: Request) -> Result {
+        response()
+            .header(CONTENT_TYPE, PEM_CONTENT_TYPE)
+            .body(self.0.clone())
+    }
+}
+
+impl From<String> for CaCertificate {
+    fn from(pem: String) -> Self {
+        CaCertificate(pem.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use hyper::http::StatusCode;
+
+    #[tokio::test]
+    async fn test_ca_certificate() {
+        let handler = CaCertificate::from(String::from("PEM"));
+        let res = request().handle(handler).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_TYPE], PEM_CONTENT_TYPE);
+        let body = res.read_body_utf8().await.unwrap();
+        assert_eq!(body, "PEM");
+    }
+}
@@ -1,10 +1,14 @@
 use crate::headers::UserAgent;
-use crate::http::{Request, Result, bad_request, ok};
+use crate::http::{Format, Request, Result, bad_request, json, ok};
+use serde_json::json;
 
 pub async fn user_agent(req: Request) -> Result {
     let agent = req.typed_header::<UserAgent>().ok_or_else(bad_request)?;
 
-    ok(agent.to_string())
+    match req.format() {
+        Format::Text => ok(agent.to_string()),
+        Format::Json => json(&json!({ "user-agent": agent.as_str() })),
+    }
 }
 
 #[cfg(test)]
@@ -32,4 +36,18 @@ mod test {
         let body = res.read_body_utf8().await.unwrap();
         assert_eq!(body, "HTTPBoxBot/1.0");
     }
+
+    #[tokio::test]
+    async fn test_user_agent_json() {
+        let res = request()
+            .header("user-agent", "HTTPBoxBot/1.0")
+            .header("accept", "application/json")
+            .handle(user_agent)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.read_body_json().await.unwrap();
+        assert_eq!(body, json!({ "user-agent": "HTTPBoxBot/1.0" }));
+    }
 }
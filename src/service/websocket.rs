@@ -0,0 +1,441 @@
+use crate::headers::{
+    Connection, SecWebsocketAccept, SecWebsocketKey, SecWebsocketVersion,
+    Upgrade,
+};
+use crate::http::{Bytes, Request, Result, StatusCode, bad_request, response};
+use crate::random::rng;
+use futures::prelude::*;
+use hyper_util::rt::TokioIo;
+use rand::Rng;
+use serde_derive::Deserialize;
+use std::cmp::min;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::frame::Frame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{
+    CloseCode, Data, OpCode,
+};
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, Role};
+
+const DEFAULT_MESSAGE_SIZE: usize = 32;
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+/// Most messages `/ws/stream/{n}` sends, whatever `n` is
+const MAX_MESSAGES: usize = 100;
+
+#[derive(Deserialize, Default, Clone)]
+pub struct WebSocketParams {
+    #[serde(default)]
+    ping: bool,
+    close_code: Option<u16>,
+    close_reason: Option<String>,
+    limit: Option<usize>,
+    drop_after: Option<usize>,
+    fragment: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct StreamParams {
+    seed: Option<u32>,
+    size: Option<usize>,
+}
+
+fn data_parts(message: &Message) -> Option<(Data, Bytes)> {
+    match message {
+        Message::Text(text) => Some((Data::Text, Bytes::from(text.clone()))),
+        Message::Binary(data) => Some((Data::Binary, data.clone())),
+        _ => None,
+    }
+}
+
+/// Split a data message into a leading frame and continuation frames
+fn fragments(opcode: Data, payload: Bytes, size: usize) -> Vec<Frame> {
+    let size = size.max(1);
+    if payload.len() <= size {
+        return vec![Frame::message(payload, OpCode::Data(opcode), true)];
+    }
+
+    let count = payload.len().div_ceil(size);
+    (0..count)
+        .map(|idx| {
+            let start = idx * size;
+            let end = min(start + size, payload.len());
+            let opcode = if idx == 0 { opcode } else { Data::Continue };
+            Frame::message(
+                payload.slice(start..end),
+                OpCode::Data(opcode),
+                idx == count - 1,
+            )
+        })
+        .collect()
+}
+
+/// The wire encoding of a frame cut off halfway through its payload
+fn truncated(frame: Frame) -> anyhow::Result<Vec<u8>> {
+    let payload_len = frame.payload().len();
+    let mut buf = vec![];
+    frame.format(&mut buf)?;
+    buf.truncate(buf.len() - payload_len.div_ceil(2));
+    Ok(buf)
+}
+
+fn stream_messages(
+    n: usize,
+    size: usize,
+    seed: Option<u32>,
+) -> impl Iterator<Item = Bytes> {
+    let mut rng = rng(seed);
+    (0..min(n, MAX_MESSAGES)).map(move |_| {
+        (0..size)
+            .map(|_| rng.random::<u8>())
+            .collect::<Vec<u8>>()
+            .into()
+    })
+}
+
+struct Session<S> {
+    ws: WebSocketStream<S>,
+    params: WebSocketParams,
+    sent: usize,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    fn new(ws: WebSocketStream<S>, params: WebSocketParams) -> Self {
+        Self {
+            ws,
+            params,
+            sent: 0,
+        }
+    }
+
+    async fn send(&mut self, message: Message) -> anyhow::Result<()> {
+        if self.params.ping {
+            self.ws.send(Message::Ping(Bytes::new())).await?;
+        }
+
+        let parts = data_parts(&message);
+
+        if self.params.drop_after == Some(self.sent) {
+            if let Some((opcode, payload)) = parts {
+                let frame = Frame::message(payload, OpCode::Data(opcode), true);
+                let buf = truncated(frame)?;
+                let stream = self.ws.get_mut();
+                stream.write_all(&buf).await?;
+                stream.shutdown().await?;
+            }
+            anyhow::bail!("connection dropped mid-message");
+        }
+
+        match (self.params.fragment, parts) {
+            (Some(size), Some((opcode, payload))) => {
+                for frame in fragments(opcode, payload, size) {
+                    self.ws.feed(Message::Frame(frame)).await?;
+                }
+                self.ws.flush().await?;
+            }
+            _ => self.ws.send(message).await?,
+        }
+
+        self.sent += 1;
+        Ok(())
+    }
+
+    async fn close(&mut self) -> anyhow::Result<()> {
+        let frame = CloseFrame {
+            code: self.params.close_code.map_or(CloseCode::Normal, Into::into),
+            reason: self.params.close_reason.clone().unwrap_or_default().into(),
+        };
+        self.ws.close(Some(frame)).await?;
+        self.finish().await
+    }
+
+    /// Drive the stream until the closing handshake completes
+    async fn finish(&mut self) -> anyhow::Result<()> {
+        while self.ws.next().await.transpose()?.is_some() {}
+        Ok(())
+    }
+}
+
+async fn echo_session<S: AsyncRead + AsyncWrite + Unpin>(
+    ws: WebSocketStream<S>,
+    params: WebSocketParams,
+) -> anyhow::Result<()> {
+    let mut session = Session::new(ws, params);
+
+    while let Some(message) = session.ws.next().await.transpose()? {
+        if data_parts(&message).is_none() {
+            // Control frames are answered by the protocol implementation
+            continue;
+        }
+
+        session.send(message).await?;
+        if session.params.limit == Some(session.sent) {
+            return session.close().await;
+        }
+    }
+
+    Ok(())
+}
+
+async fn stream_session<S: AsyncRead + AsyncWrite + Unpin>(
+    ws: WebSocketStream<S>,
+    params: WebSocketParams,
+    messages: impl Iterator<Item = Bytes>,
+) -> anyhow::Result<()> {
+    let mut session = Session::new(ws, params);
+
+    for message in messages {
+        session.send(Message::Binary(message)).await?;
+    }
+
+    session.close().await
+}
+
+#[allow(clippy::result_large_err)]
+fn upgrade<F, Fut>(mut req: Request, session: F) -> Result
+where
+    F: FnOnce(WebSocketStream<TokioIo<hyper::upgrade::Upgraded>>) -> Fut
+        + Send
+        + 'static,
+    Fut: Future<Output = anyhow::Result<()>> + Send,
+{
+    req.typed_header::<Upgrade>()
+        .filter(|upgrade| upgrade == &Upgrade::websocket())
+        .ok_or_else(bad_request)?;
+    req.typed_header::<Connection>()
+        .filter(|connection| connection.contains("upgrade"))
+        .ok_or_else(bad_request)?;
+    req.typed_header::<SecWebsocketVersion>()
+        .filter(|version| version == &SecWebsocketVersion::V13)
+        .ok_or_else(bad_request)?;
+    let key = req
+        .typed_header::<SecWebsocketKey>()
+        .ok_or_else(bad_request)?;
+
+    let on_upgrade = hyper::upgrade::on(&mut *req);
+    tokio::task::spawn(async move {
+        let upgraded = match on_upgrade.await {
+            Ok(upgraded) => upgraded,
+            Err(err) => {
+                tracing::error!("Error upgrading connection: {err:#}");
+                return;
+            }
+        };
+
+        let ws = WebSocketStream::from_raw_socket(
+            TokioIo::new(upgraded),
+            Role::Server,
+            None,
+        )
+        .await;
+
+        if let Err(err) = session(ws).await {
+            tracing::debug!("WebSocket session ended: {err:#}");
+        }
+    });
+
+    response()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .typed_header(Connection::upgrade())
+        .typed_header(Upgrade::websocket())
+        .typed_header(SecWebsocketAccept::from(key))
+        .into()
+}
+
+pub async fn echo(req: Request) -> Result {
+    let params = req.query::<WebSocketParams>().map_err(|_| bad_request())?;
+
+    upgrade(req, move |ws| echo_session(ws, params))
+}
+
+pub async fn stream(req: Request) -> Result {
+    let n = req.param::<usize>("n").ok_or_else(bad_request)?;
+    let params = req.query::<WebSocketParams>().map_err(|_| bad_request())?;
+    let query = req.query::<StreamParams>().map_err(|_| bad_request())?;
+
+    let size =
+        min(query.size.unwrap_or(DEFAULT_MESSAGE_SIZE), MAX_MESSAGE_SIZE);
+    let messages = stream_messages(n, size, query.seed);
+
+    upgrade(req, move |ws| stream_session(ws, params, messages))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::*;
+    use tokio::io::{AsyncReadExt, DuplexStream, duplex};
+
+    const KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+
+    fn upgrade_request() -> crate::test::RequestBuilder {
+        request()
+            .header("connection", "Upgrade")
+            .header("upgrade", "websocket")
+            .header("sec-websocket-version", "13")
+            .header("sec-websocket-key", KEY)
+    }
+
+    async fn pair()
+    -> (WebSocketStream<DuplexStream>, WebSocketStream<DuplexStream>) {
+        let (server, client) = duplex(1024 * 1024);
+        let server =
+            WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+        let client =
+            WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn test_echo_upgrade() {
+        let res = upgrade_request().handle(echo).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(
+            res.headers()["sec-websocket-accept"],
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[tokio::test]
+    async fn test_echo_not_upgrade() {
+        let res = request().handle(echo).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_stream_bad_param() {
+        let res = upgrade_request()
+            .param("n", "abc")
+            .handle(stream)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_echo_session() {
+        let (server, mut client) = pair().await;
+        let params = WebSocketParams {
+            limit: Some(2),
+            close_code: Some(4000),
+            ..Default::default()
+        };
+        let server = tokio::spawn(echo_session(server, params));
+
+        client.send(Message::text("hello")).await.unwrap();
+        client.send(Message::binary(vec![1, 2, 3])).await.unwrap();
+
+        assert_eq!(
+            client.next().await.unwrap().unwrap(),
+            Message::text("hello")
+        );
+        assert_eq!(
+            client.next().await.unwrap().unwrap(),
+            Message::binary(vec![1, 2, 3])
+        );
+        match client.next().await.unwrap().unwrap() {
+            Message::Close(Some(frame)) => {
+                assert_eq!(frame.code, CloseCode::from(4000))
+            }
+            message => panic!("Unexpected message: {message:?}"),
+        }
+        while client.next().await.is_some() {}
+
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_echo_session_fragmented() {
+        let (server, mut client) = pair().await;
+        let params = WebSocketParams {
+            fragment: Some(2),
+            ..Default::default()
+        };
+        tokio::spawn(echo_session(server, params));
+
+        client.send(Message::text("hello")).await.unwrap();
+        assert_eq!(
+            client.next().await.unwrap().unwrap(),
+            Message::text("hello")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_session() {
+        let (server, client) = pair().await;
+        let messages = stream_messages(3, 4, Some(1234));
+        tokio::spawn(stream_session(
+            server,
+            WebSocketParams::default(),
+            messages,
+        ));
+
+        let received = client
+            .take_while(|message| {
+                future::ready(matches!(message, Ok(Message::Binary(_))))
+            })
+            .map(|message| message.unwrap().into_data())
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(received.len(), 3);
+        assert_eq!(received[0].as_ref(), [214, 212, 32, 32]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_session_drop() {
+        let (server, mut client) = duplex(1024);
+        let server =
+            WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+        let params = WebSocketParams {
+            drop_after: Some(0),
+            ..Default::default()
+        };
+        let messages = stream_messages(1, 4, Some(1234));
+        let result = stream_session(server, params, messages).await;
+        assert!(result.is_err());
+
+        let mut buf = vec![];
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, [0x82, 4, 214, 212]);
+    }
+
+    #[test]
+    fn test_fragments() {
+        let frames = fragments(Data::Text, Bytes::from_static(b"hello"), 2);
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].header().opcode, OpCode::Data(Data::Text));
+        assert!(!frames[0].header().is_final);
+        assert_eq!(frames[1].header().opcode, OpCode::Data(Data::Continue));
+        assert_eq!(frames[2].payload(), b"o");
+        assert!(frames[2].header().is_final);
+    }
+
+    #[test]
+    fn test_fragments_small_payload() {
+        let frames = fragments(Data::Binary, Bytes::from_static(b"hi"), 16);
+
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].header().is_final);
+    }
+
+    #[test]
+    fn test_stream_messages_seeded() {
+        let first = stream_messages(2, 8, Some(1)).collect::<Vec<_>>();
+        let second = stream_messages(2, 8, Some(1)).collect::<Vec<_>>();
+
+        assert_eq!(first, second);
+        assert_eq!(first[0].len(), 8);
+    }
+
+    #[test]
+    fn test_stream_messages_capped() {
+        let messages = stream_messages(MAX_MESSAGES + 1, 1, Some(1));
+
+        assert_eq!(messages.count(), MAX_MESSAGES);
+    }
+}
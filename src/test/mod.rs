@@ -2,5 +2,5 @@ pub(crate) mod headers;
 mod request;
 mod response;
 
-pub use request::request;
+pub use request::{RequestBuilder, request};
 pub use response::TestResponseExt;
@@ -5,9 +5,10 @@ use crate::headers::ContentLength;
 use crate::headers::{Header, HeaderMapExt};
 use crate::http::{Body, Request};
 use futures::prelude::*;
-use hyper::Method;
 use hyper::header::{HeaderName, HeaderValue};
+use hyper::http::uri::Scheme;
 use hyper::http::{Request as HTTPRequest, Response as HTTPResponse};
+use hyper::{Method, Version};
 use std::convert::TryFrom;
 use std::net::SocketAddr;
 use uri_path::PathMatch;
@@ -24,6 +25,11 @@ impl RequestBuilder {
         self
     }
 
+    pub fn version(mut self, version: Version) -> Self {
+        *self.req.version_mut() = version;
+        self
+    }
+
     pub fn path(mut self, p: &str) -> Self {
         let uri = p.parse().expect("test request path invalid");
         *self.req.uri_mut() = uri;
@@ -62,11 +68,24 @@ impl RequestBuilder {
         self.typed_header(ContentLength(len as u64))
     }
 
+    pub fn extension<T: Clone + Send + Sync + 'static>(
+        mut self,
+        ext: T,
+    ) -> Self {
+        self.req.extensions_mut().insert(ext);
+        self
+    }
+
     pub fn client_addr(mut self, addr: SocketAddr) -> Self {
         self.client_addr = Some(addr);
         self
     }
 
+    pub fn scheme(mut self, scheme: Scheme) -> Self {
+        self.req.extensions_mut().insert(scheme);
+        self
+    }
+
     pub fn build(self) -> Request {
         let mut req = self.req;
 
@@ -8,6 +8,9 @@ pub trait TestResponseExt: Sized {
     async fn read_body_utf8(self) -> anyhow::Result<String> {
         Ok(String::from_utf8(self.read_body().await?)?)
     }
+    async fn read_body_json(self) -> anyhow::Result<serde_json::Value> {
+        Ok(serde_json::from_slice(&self.read_body().await?)?)
+    }
 }
 
 #[async_trait]
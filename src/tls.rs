@@ -0,0 +1,149 @@
+use crate::server::Protocol;
+use anyhow::Context;
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+    Issuer, KeyPair, KeyUsagePurpose,
+};
+use rustls::ServerConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::TlsAcceptor;
+
+const CA_COMMON_NAME: &str = "httpbox Test CA";
+
+fn alpn_protocols(protocol: Protocol) -> Vec<Vec<u8>> {
+    match protocol {
+        Protocol::Auto => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        Protocol::Http1 => vec![b"http/1.1".to_vec()],
+        Protocol::Http2 => vec![b"h2".to_vec()],
+    }
+}
+
+fn server_config(
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    protocol: Protocol,
+) -> anyhow::Result<ServerConfig> {
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(chain, key)?;
+    config.alpn_protocols = alpn_protocols(protocol);
+    Ok(config)
+}
+
+pub struct Tls {
+    config: Arc<ServerConfig>,
+    ca_pem: Option<String>,
+}
+
+impl Tls {
+    pub fn from_pem_files(
+        cert: &Path,
+        key: &Path,
+        protocol: Protocol,
+    ) -> anyhow::Result<Self> {
+        let chain = CertificateDer::pem_file_iter(cert)
+            .and_then(Iterator::collect)
+            .with_context(|| format!("reading {}", cert.display()))?;
+        let key = PrivateKeyDer::from_pem_file(key)
+            .with_context(|| format!("reading {}", key.display()))?;
+
+        Ok(Self {
+            config: Arc::new(server_config(chain, key, protocol)?),
+            ca_pem: None,
+        })
+    }
+
+    /// Generate an in-memory CA and a leaf certificate for `hosts` signed by it
+    pub fn self_signed(
+        hosts: Vec<String>,
+        protocol: Protocol,
+    ) -> anyhow::Result<Self> {
+        let mut ca_params = CertificateParams::new(vec![])?;
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, CA_COMMON_NAME);
+        ca_params.key_usages = vec![
+            KeyUsagePurpose::DigitalSignature,
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+        ];
+        let ca_key = KeyPair::generate()?;
+        let ca_cert = ca_params.self_signed(&ca_key)?;
+        let issuer = Issuer::new(ca_params, ca_key);
+
+        let mut leaf_params = CertificateParams::new(hosts)?;
+        leaf_params
+            .distinguished_name
+            .push(DnType::CommonName, "localhost");
+        leaf_params.use_authority_key_identifier_extension = true;
+        leaf_params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        leaf_params.extended_key_usages =
+            vec![ExtendedKeyUsagePurpose::ServerAuth];
+        let leaf_key = KeyPair::generate()?;
+        let leaf_cert = leaf_params.signed_by(&leaf_key, &issuer)?;
+
+        let chain = vec![leaf_cert.der().clone(), ca_cert.der().clone()];
+        let key =
+            PrivateKeyDer::from_pem_slice(leaf_key.serialize_pem().as_bytes())?;
+
+        Ok(Self {
+            config: Arc::new(server_config(chain, key, protocol)?),
+            ca_pem: Some(ca_cert.pem()),
+        })
+    }
+
+    pub fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.config.clone())
+    }
+
+    /// The PEM encoded CA certificate when running with a self-signed chain
+    pub fn ca_pem(&self) -> Option<&str> {
+        self.ca_pem.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_self_signed() {
+        let tls = Tls::self_signed(
+            vec!["localhost".to_owned(), "127.0.0.1".to_owned()],
+            Protocol::Auto,
+        )
+        .unwrap();
+
+        assert!(
+            tls.ca_pem()
+                .unwrap()
+                .starts_with("-----BEGIN CERTIFICATE-----")
+        );
+        assert_eq!(
+            tls.config.alpn_protocols,
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_alpn_protocols() {
+        assert_eq!(alpn_protocols(Protocol::Http1), vec![b"http/1.1".to_vec()]);
+        assert_eq!(alpn_protocols(Protocol::Http2), vec![b"h2".to_vec()]);
+    }
+
+    #[test]
+    fn test_from_missing_pem_files() {
+        assert!(
+            Tls::from_pem_files(
+                Path::new("/nonexistent/cert.pem"),
+                Path::new("/nonexistent/key.pem"),
+                Protocol::Auto,
+            )
+            .is_err()
+        );
+    }
+}
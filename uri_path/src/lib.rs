@@ -1,7 +1,6 @@
 #[macro_use]
 mod macros;
 
-use itertools::EitherOrBoth;
 use itertools::Itertools;
 #[cfg(feature = "regex")]
 pub use regex;
@@ -61,18 +60,24 @@ pub struct Path(pub Vec<PathSegment>);
 impl Path {
     pub fn matches(&self, path: &str) -> Option<PathMatch> {
         let mut params = PathMatch::default();
-        for el in self.iter().zip_longest(segmented(path)) {
-            match el {
-                EitherOrBoth::Both(expected, actual) => {
-                    if !expected.matches(actual) {
-                        return None;
-                    }
-                    if let PathSegment::Dynamic(param) = expected {
-                        params.insert(param.name, actual.to_owned());
-                    }
-                }
-                _ => return None,
+        let mut actual = segmented(path);
+        for expected in self.iter() {
+            if let PathSegment::CatchAll(name) = expected {
+                params.insert(name, actual.join("/"));
+                return Some(params);
+            }
+
+            let actual = actual.next()?;
+            if !expected.matches(actual) {
+                return None;
             }
+            if let PathSegment::Dynamic(param) = expected {
+                params.insert(param.name, actual.to_owned());
+            }
+        }
+
+        if actual.next().is_some() {
+            return None;
         }
         Some(params)
     }
@@ -93,6 +98,11 @@ impl Path {
                     let value = params.remove(param.name)?;
                     segments.push(value)
                 }
+                PathSegment::CatchAll(name) => {
+                    if let Some(value) = params.remove(name) {
+                        segments.push(value)
+                    }
+                }
             }
         }
 
@@ -173,6 +183,8 @@ impl fmt::Display for PathParam {
 pub enum PathSegment {
     Literal(&'static str),
     Dynamic(PathParam),
+    /// Matches all remaining segments (including none), must be last
+    CatchAll(&'static str),
 }
 
 impl PathSegment {
@@ -180,6 +192,7 @@ impl PathSegment {
         match self {
             Self::Literal(str) => str == &path,
             Self::Dynamic(param) => param.token.matches(path),
+            Self::CatchAll(_) => true,
         }
     }
 }
@@ -189,6 +202,7 @@ impl fmt::Display for PathSegment {
         match self {
             Self::Literal(str) => write!(f, "{}", str),
             Self::Dynamic(param) => write!(f, "{}", param),
+            Self::CatchAll(name) => write!(f, "*{}", name),
         }
     }
 }
@@ -9,6 +9,9 @@ macro_rules! path {
             $crate::PathToken::Any,
         ))
     };
+    (@segment [* $i:ident]) => {
+        $crate::PathSegment::CatchAll(stringify!($i))
+    };
     (@segment [$i:ident ~ $re:literal]) => {{
         $crate::PathSegment::Dynamic($crate::PathParam::new(
             stringify!($i),
@@ -107,9 +107,40 @@ path_test! {
             {param: "value"} => "/test/value",
             {param: "value", first: "other", second: "another"} => "/test/value?first=other&second=another",
         },
+    },
+    catch_all(path!("test" / [*rest])) {
+        matches: ["/test", "/test/", "/test/abc", "/test/abc/whatever"],
+        non_matches: ["/", "/other", "/other/test"],
+        params: {
+            "/test" => {rest: ""},
+            "/test/abc" => {rest: "abc"},
+            "/test/abc/whatever" => {rest: "abc/whatever"},
+            "/other" => None,
+        },
+        replace: {
+            {} => "/test",
+            {rest: "abc/whatever"} => "/test/abc/whatever",
+            {rest: "abc", first: "other"} => "/test/abc?first=other",
+        },
+    },
+    param_then_catch_all(path!("test" / param / [*rest])) {
+        matches: ["/test/abc", "/test/abc/whatever/else"],
+        non_matches: ["/test"],
+        params: {
+            "/test/abc" => {param: "abc", rest: ""},
+            "/test/abc/whatever/else" => {param: "abc", rest: "whatever/else"},
+        },
     }
 }
 
+#[test]
+fn test_display_catch_all() {
+    assert_eq!(
+        path!("test" / param / [*rest]).to_string(),
+        "/test/:param/*rest"
+    );
+}
+
 #[cfg(feature = "regex")]
 path_test! {
     regex_digits(path!("test" / [param ~ r"\d+"])) {